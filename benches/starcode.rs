@@ -0,0 +1,175 @@
+use std::{fs::read_to_string, hint::black_box};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use num::BigInt;
+use runling_run_edit::{starcode, Account, Bank, BankRegion, Runling, XmlBank};
+
+/// The starcode functions as they were before the codec used lookup tables and radix conversion,
+/// kept to show the speedup
+mod baseline {
+    use num::{BigInt, FromPrimitive};
+    use runling_run_edit::{Error, Result};
+
+    const ALPHABET: &str =
+        "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ!$%/()=?,.-;:_^#+* @{[]}|~`";
+    const ENCRYPTION_KEY: &str = "WalkerKey";
+
+    fn alphabet_index(c: char) -> Result<usize> {
+        ALPHABET
+            .char_indices()
+            .find_map(|(i, a)| if a == c { Some(i) } else { None })
+            .ok_or(Error::InvalidCharacter(c))
+    }
+
+    fn shift_backward(c: char, d: char) -> Result<char> {
+        let c_index = alphabet_index(c)?;
+        let d_index = alphabet_index(d)?;
+        let result_index = ((c_index + ALPHABET.len()) - d_index) % ALPHABET.len();
+        Ok(ALPHABET.chars().nth(result_index).unwrap())
+    }
+    fn shift_forward(c: char, d: char) -> Result<char> {
+        let c_index = alphabet_index(c)?;
+        let d_index = alphabet_index(d)?;
+        let result_index = ((c_index + ALPHABET.len()) + d_index) % ALPHABET.len();
+        Ok(ALPHABET.chars().nth(result_index).unwrap())
+    }
+
+    pub fn decrypt(s: String) -> Result<String> {
+        let mut result: String = String::new();
+        for (i, c) in s.char_indices() {
+            let key_index = i % ENCRYPTION_KEY.len();
+            result.push(shift_backward(
+                c,
+                ENCRYPTION_KEY.chars().nth(key_index).unwrap(),
+            )?)
+        }
+        Ok(result)
+    }
+    pub fn encrypt(s: String) -> Result<String> {
+        let mut result: String = String::new();
+        for (i, c) in s.char_indices() {
+            let key_index = i % ENCRYPTION_KEY.len();
+            result.push(shift_forward(
+                c,
+                ENCRYPTION_KEY.chars().nth(key_index).unwrap(),
+            )?)
+        }
+        Ok(result)
+    }
+
+    pub fn compress(mut s: BigInt) -> String {
+        let base = BigInt::from_usize(ALPHABET.len()).unwrap();
+        let mut remainder: BigInt;
+        let mut result = String::new();
+
+        while s != BigInt::ZERO {
+            remainder = &s % &base;
+            result.push(
+                ALPHABET
+                    .chars()
+                    .nth(remainder.to_string().parse().unwrap())
+                    .unwrap(),
+            );
+            s /= &base;
+        }
+
+        result.chars().rev().collect()
+    }
+    pub fn uncompress(s: String) -> Result<BigInt> {
+        let base = BigInt::from_usize(ALPHABET.len()).unwrap();
+        let mut power = BigInt::from_usize(1).unwrap();
+        let mut result = BigInt::ZERO;
+
+        for c in s.chars().rev() {
+            let index = alphabet_index(c)?;
+            let digit_value = &power * BigInt::from_usize(index).unwrap();
+            result += digit_value;
+            power = &power * &base;
+        }
+
+        Ok(result)
+    }
+}
+
+/// Each operation is measured with the current codec and with the [`baseline`] it replaced
+fn codec(c: &mut Criterion) {
+    let account = read_to_string("./data/account.txt").unwrap();
+    let decrypted = starcode::decrypt(account.clone()).unwrap();
+    let uncompressed = starcode::uncompress(decrypted.clone()).unwrap();
+    assert_eq!(baseline::decrypt(account.clone()).unwrap(), decrypted);
+    assert_eq!(
+        baseline::uncompress(decrypted.clone()).unwrap(),
+        BigInt::from(uncompressed.clone())
+    );
+    let signed = BigInt::from(uncompressed.clone());
+
+    let mut group = c.benchmark_group("decrypt");
+    group.bench_function("codec", |b| {
+        b.iter(|| starcode::decrypt(black_box(account.clone())))
+    });
+    group.bench_function("baseline", |b| {
+        b.iter(|| baseline::decrypt(black_box(account.clone())))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("encrypt");
+    group.bench_function("codec", |b| {
+        b.iter(|| starcode::encrypt(black_box(decrypted.clone())))
+    });
+    group.bench_function("baseline", |b| {
+        b.iter(|| baseline::encrypt(black_box(decrypted.clone())))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("uncompress");
+    group.bench_function("codec", |b| {
+        b.iter(|| starcode::uncompress(black_box(decrypted.clone())))
+    });
+    group.bench_function("baseline", |b| {
+        b.iter(|| baseline::uncompress(black_box(decrypted.clone())))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("compress");
+    group.bench_function("codec", |b| {
+        b.iter(|| starcode::compress(black_box(uncompressed.clone())))
+    });
+    group.bench_function("baseline", |b| {
+        b.iter(|| baseline::compress(black_box(signed.clone())))
+    });
+    group.finish();
+}
+
+fn records(c: &mut Criterion) {
+    let account = read_to_string("./data/account.txt").unwrap();
+    let runling = read_to_string("./data/runling-1.txt").unwrap();
+    let decoded_account = Account::from_data(account.clone()).unwrap();
+
+    c.bench_function("decode account", |b| {
+        b.iter(|| Account::from_data(black_box(account.clone())))
+    });
+    c.bench_function("encode account", |b| {
+        b.iter(|| black_box(&decoded_account).to_data())
+    });
+    c.bench_function("decode runling", |b| {
+        b.iter(|| Runling::from_data(black_box(runling.clone())))
+    });
+}
+
+fn banks(c: &mut Criterion) {
+    let raw_bank = read_to_string("./data/RunlingRun004.SC2Bank").unwrap();
+
+    c.bench_function("decode and encode bank", |b| {
+        b.iter(|| {
+            let bank = Bank::decode(
+                black_box(&raw_bank).parse::<XmlBank>().unwrap(),
+                BankRegion::default(),
+            )
+            .unwrap();
+            XmlBank::try_from(bank).unwrap()
+        })
+    });
+}
+
+criterion_group!(benches, codec, records, banks);
+criterion_main!(benches);
@@ -0,0 +1,722 @@
+use std::{fmt, str::FromStr};
+
+use num::{BigUint, Integer, ToPrimitive, Zero};
+
+use crate::{
+    error::{Error, Result},
+    starcode::{parse_payload, store_int, Codec},
+};
+
+/// What an encrypted bank value is known to hold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KnownValue {
+    /// The number it uncompresses to
+    Number(BigUint),
+    /// The values of its fields along with their maximum values, in the order they are stored
+    Fields(Vec<(usize, usize)>),
+}
+impl FromStr for KnownValue {
+    type Err = Error;
+
+    /// Parses either a number, or fields written as `VALUE/MAX` separated by commas
+    fn from_str(s: &str) -> Result<Self> {
+        if !s.contains('/') {
+            return parse_payload(s).map(KnownValue::Number);
+        }
+        s.split(',')
+            .map(|field| {
+                let (value, max_val) =
+                    field
+                        .split_once('/')
+                        .ok_or_else(|| Error::UnexpectedFormat {
+                            expected: "VALUE/MAX",
+                            found: field.to_string(),
+                        })?;
+                let parse = |n: &str| {
+                    n.trim()
+                        .parse()
+                        .map_err(|_| Error::InvalidNumber(n.to_string()))
+                };
+                Ok((parse(value)?, parse(max_val)?))
+            })
+            .collect::<Result<_>>()
+            .map(KnownValue::Fields)
+    }
+}
+impl KnownValue {
+    pub fn to_number(&self) -> Result<BigUint> {
+        match self {
+            KnownValue::Number(number) => Ok(number.clone()),
+            KnownValue::Fields(fields) => {
+                let mut data = BigUint::ZERO;
+                for &(value, max_val) in fields {
+                    store_int(&mut data, value, max_val)?;
+                }
+                Ok(data)
+            }
+        }
+    }
+}
+
+/// The repeating encryption key found by [`recover_key`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveredKey {
+    /// The length of the key, which is the shortest one that explains every sample
+    pub period: usize,
+    /// Each character of the key, or `None` where no sample was long enough to reveal it
+    pub characters: Vec<Option<char>>,
+    /// Whether the samples repeat the key at least twice. If not, the key could be longer than
+    /// `period`, and more or longer samples are needed to be sure.
+    pub confirmed: bool,
+}
+impl RecoveredKey {
+    /// The key, if every character of it is known
+    pub fn key(&self) -> Option<String> {
+        self.characters.iter().copied().collect()
+    }
+}
+
+/// Works out the key that encrypts values written with the given alphabet, from values whose
+/// ciphertext and contents are both known. Since each character is shifted along the alphabet by
+/// the matching character of the key, comparing a ciphertext with the compressed value gives the
+/// key character at every position.
+pub fn recover_key(alphabet: &str, samples: &[(String, KnownValue)]) -> Result<RecoveredKey> {
+    // A codec whose key does not shift anything, for compressing the known values
+    let first = alphabet.chars().next().unwrap_or_default().to_string();
+    let codec = Codec::new(alphabet, &first)?;
+    let len = alphabet.len();
+
+    let mut shifts: Vec<Vec<usize>> = Vec::with_capacity(samples.len());
+    for (ciphertext, value) in samples {
+        let plaintext = codec.compress(&value.to_number()?);
+        let (cipher_len, plain_len) = (ciphertext.chars().count(), plaintext.len());
+        if cipher_len != plain_len {
+            return Err(Error::SampleLengthMismatch {
+                ciphertext: cipher_len,
+                plaintext: plain_len,
+            });
+        }
+        shifts.push(
+            ciphertext
+                .chars()
+                .zip(plaintext.chars())
+                .map(|(c, p)| {
+                    let c = codec.alphabet_index(c)? as usize;
+                    let p = codec.alphabet_index(p)? as usize;
+                    Ok((c + len - p) % len)
+                })
+                .collect::<Result<_>>()?,
+        );
+    }
+
+    let longest = shifts.iter().map(Vec::len).max().unwrap_or(0);
+    for period in 1..=longest {
+        let mut key: Vec<Option<usize>> = vec![None; period];
+        let consistent = shifts.iter().all(|sample| {
+            sample.iter().enumerate().all(|(i, &shift)| {
+                let known = key[i % period].get_or_insert(shift);
+                *known == shift
+            })
+        });
+        if consistent {
+            let alphabet = alphabet.as_bytes();
+            return Ok(RecoveredKey {
+                period,
+                characters: key
+                    .into_iter()
+                    .map(|shift| shift.map(|shift| alphabet[shift] as char))
+                    .collect(),
+                confirmed: longest >= 2 * period,
+            });
+        }
+    }
+    Err(Error::NoConsistentKey)
+}
+
+/// What is known about one field of a record whose layout is being worked out
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldHint {
+    /// The field's value in each sample, where it is known
+    pub values: Vec<Option<usize>>,
+    /// The field's maximum value, if it is known
+    pub max_val: Option<usize>,
+}
+impl FieldHint {
+    /// The hints for every field, from the values known in each sample and the maximums known for
+    /// each field, all in the order the fields are stored. There are as many fields as the longest
+    /// of these lists, and whatever is missing from the shorter ones is unknown.
+    pub fn from_samples(samples: &[Vec<Option<usize>>], max_vals: &[Option<usize>]) -> Vec<Self> {
+        let field_count = samples
+            .iter()
+            .map(Vec::len)
+            .chain([max_vals.len()])
+            .max()
+            .unwrap_or_default();
+        (0..field_count)
+            .map(|index| FieldHint {
+                values: samples
+                    .iter()
+                    .map(|values| values.get(index).copied().flatten())
+                    .collect(),
+                max_val: max_vals.get(index).copied().flatten(),
+            })
+            .collect()
+    }
+}
+
+/// A field of a layout proposed by [`discover_layout`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProposedField {
+    /// `None` if any maximum that is at least as large as every value fits the samples, which
+    /// happens for the first field when nothing is stored before it
+    pub max_val: Option<usize>,
+    /// The field's value in each sample
+    pub values: Vec<usize>,
+}
+
+/// A layout that explains every sample, see [`discover_layout`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    /// The fields, in the order they are stored
+    pub fields: Vec<ProposedField>,
+    /// What is left of each payload once the fields are taken out of it. Anything but zero means
+    /// that more fields are stored before the ones that were given.
+    pub remainders: Vec<BigUint>,
+}
+
+impl fmt::Display for Layout {
+    /// One line per field with its maximum and its value in each sample, followed by a line with
+    /// the remainders if they are not all zero
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |values: Vec<String>| values.join(", ");
+        for (index, field) in self.fields.iter().enumerate() {
+            let max_val = field
+                .max_val
+                .map_or_else(|| "any".to_string(), |max_val| max_val.to_string());
+            let values = join(field.values.iter().map(ToString::to_string).collect());
+            writeln!(f, "field {index}: max {max_val:<10} values {values}")?;
+        }
+        if self.remainders.iter().any(|remainder| !remainder.is_zero()) {
+            let remainders = join(self.remainders.iter().map(ToString::to_string).collect());
+            writeln!(f, "left over before field 0: {remainders}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Trial divisors go up to this, and any factor above it is only found when it is all that is left
+const TRIAL_DIVISION_LIMIT: usize = 1_000_000;
+
+struct LayoutSearch<'a> {
+    hints: &'a [FieldHint],
+    max_radix: usize,
+    limit: usize,
+    primes: Vec<usize>,
+    layouts: Vec<Layout>,
+}
+impl LayoutSearch<'_> {
+    /// Every divisor of `n` that is larger than `above`, and no larger than `max_radix`
+    fn divisors(&self, n: &BigUint, above: usize) -> Vec<usize> {
+        let mut n = n.clone();
+        let mut factors: Vec<(usize, u32)> = Vec::new();
+        for &prime in &self.primes {
+            if prime > self.max_radix || BigUint::from(prime * prime) > n {
+                break;
+            }
+            let mut power = 0;
+            while (&n % prime).is_zero() {
+                n /= prime;
+                power += 1;
+            }
+            if power > 0 {
+                factors.push((prime, power));
+            }
+        }
+        if let Some(rest) = n
+            .to_usize()
+            .filter(|&rest| rest > 1 && rest <= self.max_radix)
+        {
+            factors.push((rest, 1));
+        }
+
+        let mut divisors = vec![1_usize];
+        for (factor, power) in factors {
+            let mut more = Vec::new();
+            for &divisor in &divisors {
+                let mut multiple = divisor;
+                for _ in 0..power {
+                    match multiple.checked_mul(factor) {
+                        Some(next) if next <= self.max_radix => multiple = next,
+                        _ => break,
+                    }
+                    more.push(multiple);
+                }
+            }
+            divisors.extend(more);
+        }
+        divisors.retain(|&divisor| divisor > above.max(1));
+        divisors.sort_unstable();
+        divisors
+    }
+
+    /// Takes the fields out of the payloads from the last one stored, trying every maximum value
+    /// that fits the known values of each field
+    fn search(
+        &mut self,
+        payloads: Vec<BigUint>,
+        field_count: usize,
+        found: &mut Vec<ProposedField>,
+    ) {
+        if self.layouts.len() >= self.limit {
+            return;
+        }
+        let Some(index) = field_count.checked_sub(1) else {
+            let mut fields = found.clone();
+            fields.reverse();
+            self.layouts.push(Layout {
+                fields,
+                remainders: payloads,
+            });
+            return;
+        };
+        let hint = &self.hints[index];
+        let known = || {
+            payloads
+                .iter()
+                .zip(&hint.values)
+                .filter_map(|(payload, value)| Some((payload, (*value)?)))
+        };
+        let largest_known = known().map(|(_, value)| value).max();
+
+        let radixes = match (hint.max_val, largest_known) {
+            (Some(max_val), _) => vec![max_val + 1],
+            (None, Some(largest_known)) => {
+                let gcd = known().fold(BigUint::ZERO, |gcd, (payload, value)| {
+                    let value = BigUint::from(value);
+                    let difference = if *payload < value {
+                        value - payload
+                    } else {
+                        payload - value
+                    };
+                    gcd.gcd(&difference)
+                });
+                if gcd.is_zero() {
+                    // Every payload is just the field's value, so this is the first field stored
+                    let values = known().map(|(_, value)| value).collect::<Vec<_>>();
+                    if values.len() == payloads.len() {
+                        found.push(ProposedField {
+                            max_val: None,
+                            values,
+                        });
+                        self.search(vec![BigUint::ZERO; payloads.len()], index, found);
+                        found.pop();
+                    }
+                    return;
+                }
+                self.divisors(&gcd, largest_known)
+            }
+            (None, None) if index == 0 => {
+                // Nothing else is stored before the first field, so what is left is its value
+                if let Some(values) = payloads.iter().map(ToPrimitive::to_usize).collect() {
+                    found.push(ProposedField {
+                        max_val: None,
+                        values,
+                    });
+                    self.search(vec![BigUint::ZERO; payloads.len()], index, found);
+                    found.pop();
+                }
+                return;
+            }
+            (None, None) => return,
+        };
+
+        for radix in radixes {
+            let radix_int = BigUint::from(radix);
+            let (rests, values): (Vec<_>, Vec<_>) = payloads
+                .iter()
+                .map(|payload| {
+                    let (rest, value) = payload.div_rem(&radix_int);
+                    (rest, value.to_usize().unwrap())
+                })
+                .unzip();
+            let fits = values
+                .iter()
+                .zip(&hint.values)
+                .all(|(value, known)| known.is_none_or(|known| known == *value));
+            if !fits {
+                continue;
+            }
+            found.push(ProposedField {
+                max_val: Some(radix - 1),
+                values,
+            });
+            self.search(rests, index, found);
+            found.pop();
+            if self.layouts.len() >= self.limit {
+                return;
+            }
+        }
+    }
+}
+
+/// Searches for mixed-radix layouts that explain the given uncompressed payloads, such as the ones
+/// [`starcode_record!`](crate::starcode_record) records are stored as. `fields` holds what is known
+/// about each field, in the order they are stored; every field needs either a known maximum or a
+/// known value in at least one sample, except for the first field. Since the last field stored is
+/// `payload % (max_val + 1)`, the maximum of a field with known values has to divide the difference
+/// between each payload and its value, so the candidates are the divisors of the differences'
+/// greatest common divisor.
+///
+/// At most `limit` layouts are returned, and fields can be at most `max_radix - 1`, where
+/// `max_radix` has to be at least 2.
+pub fn discover_layout(
+    payloads: &[BigUint],
+    fields: &[FieldHint],
+    max_radix: usize,
+    limit: usize,
+) -> Result<Vec<Layout>> {
+    if max_radix < 2 {
+        return Err(Error::RadixTooSmall(max_radix));
+    }
+    for (index, field) in fields.iter().enumerate() {
+        if let Some(max_val) = field.max_val.filter(|&max_val| max_val >= max_radix) {
+            return Err(Error::ValueTooLarge {
+                value: max_val,
+                max_val: max_radix - 1,
+            });
+        }
+        if field.values.len() > payloads.len() {
+            return Err(Error::TooManyFieldValues {
+                field: index,
+                samples: payloads.len(),
+            });
+        }
+        let unconstrained = field.max_val.is_none() && field.values.iter().all(Option::is_none);
+        if unconstrained && index > 0 {
+            return Err(Error::UnconstrainedField(index));
+        }
+    }
+
+    let sieve_limit = TRIAL_DIVISION_LIMIT.min(max_radix);
+    let mut is_prime = vec![true; sieve_limit + 1];
+    let mut primes = Vec::new();
+    for n in 2..=sieve_limit {
+        if is_prime[n] {
+            primes.push(n);
+            for multiple in (n * n..=sieve_limit).step_by(n) {
+                is_prime[multiple] = false;
+            }
+        }
+    }
+
+    let mut search = LayoutSearch {
+        hints: fields,
+        max_radix,
+        limit,
+        primes,
+        layouts: Vec::new(),
+    };
+    search.search(payloads.to_vec(), fields.len(), &mut Vec::new());
+    // Layouts that explain the whole payloads first
+    search.layouts.sort_by_key(|layout| {
+        layout
+            .remainders
+            .iter()
+            .any(|remainder| !remainder.is_zero())
+    });
+    Ok(search.layouts)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::read_to_string;
+
+    use num::BigUint;
+
+    use crate::{
+        analysis::{discover_layout, recover_key, FieldHint, KnownValue, Layout, ProposedField},
+        error::Error,
+        model::{Account, Runling},
+        starcode::{store_int, Codec, RUNLING_RUN_ALPHABET},
+        Bank, BankRegion, XmlBank,
+    };
+
+    fn known_fields(
+        fields: &[(&'static str, usize)],
+        max_val: fn(&str) -> Option<usize>,
+    ) -> KnownValue {
+        KnownValue::Fields(
+            fields
+                .iter()
+                .map(|&(name, value)| (value, max_val(name).unwrap()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn recovers_runling_run_key() {
+        let account_data = read_to_string("./data/account.txt").unwrap();
+        let account = Account::from_data(account_data.clone()).unwrap();
+        let runling_data = read_to_string("./data/runling-1.txt").unwrap();
+        let runling = Runling::from_data(runling_data.clone()).unwrap();
+
+        let recovered = recover_key(
+            RUNLING_RUN_ALPHABET,
+            &[
+                (
+                    account_data,
+                    known_fields(&account.fields(), Account::max_val),
+                ),
+                (
+                    runling_data,
+                    known_fields(&runling.fields(), Runling::max_val),
+                ),
+            ],
+        )
+        .unwrap();
+        assert_eq!(recovered.period, 9);
+        assert_eq!(recovered.key().as_deref(), Some("WalkerKey"));
+        assert!(recovered.confirmed);
+
+        // The unit info key is only 10 characters long, so it cannot confirm the key's length
+        let unit_info = "/*qYg-HY{+".to_string();
+        let flags = (0..8).map(|i| (usize::from(i < 6), 425 + i)).collect();
+        let recovered = recover_key(
+            RUNLING_RUN_ALPHABET,
+            &[(unit_info, KnownValue::Fields(flags))],
+        )
+        .unwrap();
+        assert_eq!(recovered.key().as_deref(), Some("WalkerKey"));
+        assert!(!recovered.confirmed);
+    }
+
+    #[test]
+    fn recovers_other_keys() {
+        let codec = Codec::new("0123456789abcdef", "3a3").unwrap();
+        let value = BigUint::from(0x0123_4567_89ab_cdefu64);
+        let ciphertext = codec.encrypt(&codec.compress(&value)).unwrap();
+
+        let recovered = recover_key(
+            codec.alphabet(),
+            &[(ciphertext.clone(), KnownValue::Number(value.clone()))],
+        )
+        .unwrap();
+        assert_eq!(recovered.period, 3);
+        assert_eq!(recovered.key().as_deref(), Some("3a3"));
+
+        let wrong_value = KnownValue::Number(value + 1_u32);
+        assert!(
+            recover_key(codec.alphabet(), &[(ciphertext, wrong_value)])
+                .unwrap()
+                .period
+                > 3
+        );
+        assert!(recover_key(
+            codec.alphabet(),
+            &[("12".to_string(), KnownValue::Number(BigUint::from(1_u32)))]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn parses_known_values() {
+        assert_eq!(
+            "123".parse::<KnownValue>().unwrap(),
+            KnownValue::Number(BigUint::from(123_u32))
+        );
+        assert_eq!(
+            "3/1000, 120/50000".parse::<KnownValue>().unwrap(),
+            KnownValue::Fields(vec![(3, 1000), (120, 50000)])
+        );
+        assert!(matches!(
+            "3/1000,120".parse::<KnownValue>(),
+            Err(Error::UnexpectedFormat { .. })
+        ));
+        assert!("-3".parse::<KnownValue>().is_err());
+        assert!("3/x".parse::<KnownValue>().is_err());
+    }
+
+    #[test]
+    fn field_hints_from_samples() {
+        let hints = FieldHint::from_samples(
+            &[vec![Some(1), None], vec![Some(2), Some(5), Some(7)]],
+            &[None, Some(10)],
+        );
+        assert_eq!(
+            hints,
+            [
+                FieldHint {
+                    values: vec![Some(1), Some(2)],
+                    max_val: None
+                },
+                FieldHint {
+                    values: vec![None, Some(5)],
+                    max_val: Some(10)
+                },
+                FieldHint {
+                    values: vec![None, Some(7)],
+                    max_val: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn displays_layouts() {
+        let layout = Layout {
+            fields: vec![
+                ProposedField {
+                    max_val: None,
+                    values: vec![1, 2],
+                },
+                ProposedField {
+                    max_val: Some(999),
+                    values: vec![12, 0],
+                },
+            ],
+            remainders: vec![BigUint::ZERO, BigUint::ZERO],
+        };
+        assert_eq!(
+            layout.to_string(),
+            "field 0: max any        values 1, 2\nfield 1: max 999        values 12, 0\n"
+        );
+
+        let layout = Layout {
+            remainders: vec![BigUint::from(4_u32), BigUint::ZERO],
+            ..layout
+        };
+        assert!(layout
+            .to_string()
+            .ends_with("left over before field 0: 4, 0\n"));
+    }
+
+    #[test]
+    fn discovers_runling_layout() {
+        let xml_bank = read_to_string("./data/RunlingRun004.SC2Bank")
+            .unwrap()
+            .parse::<XmlBank>()
+            .unwrap();
+        let bank = Bank::decode(xml_bank, BankRegion::default()).unwrap();
+        let runlings = bank.units.iter().flatten().collect::<Vec<_>>();
+        let codec = Codec::runling_run();
+        let payloads = runlings
+            .iter()
+            .map(|runling| {
+                let data = runling.encode(codec).unwrap();
+                codec.uncompress(&codec.decrypt(&data).unwrap()).unwrap()
+            })
+            .collect::<Vec<_>>();
+        let fields = (0..Runling::SCHEMA.len())
+            .map(|index| FieldHint {
+                values: runlings
+                    .iter()
+                    .map(|runling| Some(runling.fields()[index].1))
+                    .collect(),
+                max_val: None,
+            })
+            .collect::<Vec<_>>();
+
+        let layouts = discover_layout(&payloads, &fields, 100_000_000, 1000).unwrap();
+        let expected = Runling::SCHEMA
+            .iter()
+            .enumerate()
+            .map(|(index, spec)| (index > 0).then_some(spec.max_val))
+            .collect::<Vec<_>>();
+        assert!(layouts.iter().any(|layout| {
+            layout
+                .fields
+                .iter()
+                .map(|field| field.max_val)
+                .eq(expected.iter().copied())
+        }));
+        assert!(layouts[0]
+            .remainders
+            .iter()
+            .all(|remainder| *remainder == BigUint::ZERO));
+    }
+
+    #[test]
+    fn discovers_partially_known_layouts() {
+        let max_vals = [50, 1000, 7, 999];
+        let samples = [[3, 999, 0, 12], [50, 1, 7, 998], [17, 500, 3, 0]];
+        let payloads = samples
+            .iter()
+            .map(|values| {
+                let mut payload = BigUint::from(42_u32);
+                for (&value, &max_val) in values.iter().zip(&max_vals) {
+                    store_int(&mut payload, value, max_val).unwrap();
+                }
+                payload
+            })
+            .collect::<Vec<_>>();
+        let field = |index: usize, known: &[usize], max_val| FieldHint {
+            values: known
+                .iter()
+                .map(|&sample| Some(samples[sample][index]))
+                .collect(),
+            max_val,
+        };
+
+        // The third field is only known to be at most 7, and nothing is known about the first
+        let fields = [
+            field(0, &[], None),
+            field(1, &[0, 1, 2], None),
+            field(2, &[], Some(7)),
+            field(3, &[0, 1], None),
+        ];
+        assert!(discover_layout(
+            &payloads,
+            &[fields[1].clone(), fields[0].clone()],
+            10_000,
+            10
+        )
+        .is_err());
+
+        // Without the first field, what is stored before the others is left over
+        let layouts = discover_layout(&payloads, &fields[1..], 10_000, 100).unwrap();
+        let layout = layouts
+            .iter()
+            .find(|layout| {
+                layout
+                    .fields
+                    .iter()
+                    .map(|field| field.max_val)
+                    .eq([1000, 7, 999].map(Some))
+            })
+            .unwrap();
+        assert_eq!(layout.fields[1].values, [0, 7, 3]);
+        assert_eq!(layout.fields[2].values, [12, 998, 0]);
+        assert_eq!(
+            layout.remainders,
+            samples.map(|values| BigUint::from(42 * 51 + values[0]))
+        );
+
+        // Known maximums have to fit the largest radix
+        let too_large = FieldHint {
+            values: Vec::new(),
+            max_val: Some(usize::MAX),
+        };
+        assert!(matches!(
+            discover_layout(&payloads, &[too_large], usize::MAX, 10),
+            Err(Error::ValueTooLarge { .. })
+        ));
+        // A radix below 2 leaves no room for any field
+        let known = [FieldHint {
+            values: vec![Some(0)],
+            max_val: None,
+        }];
+        for max_radix in [0, 1] {
+            assert!(matches!(
+                discover_layout(&[BigUint::from(5_u32)], &known, max_radix, 10),
+                Err(Error::RadixTooSmall(radix)) if radix == max_radix
+            ));
+        }
+
+        // With it, the first field takes whatever is left
+        let layouts = discover_layout(&payloads, &fields, 10_000, 100).unwrap();
+        assert!(layouts.iter().any(|layout| {
+            layout.fields[0].max_val.is_none()
+                && layout.fields[0].values == samples.map(|values| 42 * 51 + values[0])
+        }));
+    }
+}
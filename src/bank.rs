@@ -1,48 +1,95 @@
-use num::BigInt;
-use quick_xml::se::Serializer;
-use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use num::BigUint;
+use quick_xml::{
+    events::{BytesDecl, BytesEnd, BytesStart, Event},
+    DeError, Writer,
+};
+use serde::{
+    de::{self, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
 use sha1::{Digest, Sha1};
 
 use crate::{
+    error::{Error, Result},
     model::{Account, Runling},
+    region::{BankRegion, Handle},
     starcode::{self, get_int, store_int},
 };
 
-fn derive_player_handle(checksum: String, runlings: &[Option<Runling>; 8]) -> usize {
-    let mut data = starcode::uncompress(starcode::decrypt(checksum));
+/// Splits the camera key into the account checksum and the sum of the runling checksums and player handle
+fn decode_camera_checksum(checksum: String) -> Result<(usize, usize)> {
+    let mut data = starcode::uncompress(starcode::decrypt(checksum)?)?;
     let runling_checksums = get_int(&mut data, 98000000);
-    let handle = runling_checksums
-        - runlings
-            .iter()
-            .flatten()
-            .map(|r| r.checksum())
-            .sum::<usize>();
-    handle
+    let account_checksum = get_int(&mut data, 99000000);
+    Ok((account_checksum, runling_checksums))
+}
+
+fn decode_unit_slots_data(info: String) -> Result<[bool; 8]> {
+    let mut data = starcode::uncompress(starcode::decrypt(info)?)?;
+    let mut occupied = [false; 8];
+    for unit_index in (0..8).rev() {
+        occupied[unit_index] = get_int(&mut data, 425 + unit_index) == 1;
+    }
+    Ok(occupied)
 }
 
-fn compute_signature(player_handle: usize, mut sections: Vec<Section>) -> String {
-    const HANDLE_PREFIX_NA: &str = "1-S2-1-";
-    const AUTHOR_HANDLE_NA: usize = 417073;
-    const BANK_FILENAME: &str = "RunlingRun004";
+fn derive_player_handle(checksum: String, runlings: &[Option<Runling>; 8]) -> Result<usize> {
+    let (_, runling_checksums) = decode_camera_checksum(checksum)?;
+    let unit_checksums = runlings
+        .iter()
+        .flatten()
+        .map(|r| r.checksum())
+        .sum::<usize>();
+    runling_checksums
+        .checked_sub(unit_checksums)
+        .ok_or(Error::PlayerHandleUnderflow {
+            stored: runling_checksums,
+            computed: unit_checksums,
+        })
+}
 
+/// The name of Runling Run 4's bank file, without the `.SC2Bank` extension
+pub const BANK_NAME: &str = "RunlingRun004";
+
+fn compute_signature(region: &BankRegion, player_handle: usize, sections: &[Section]) -> String {
+    signature_of(
+        BANK_NAME,
+        &region.author,
+        &region.player_handle(player_handle),
+        sections,
+    )
+}
+
+/// Computes the signature SC2 expects for a bank of any map. `bank_name` is the bank's file name
+/// without `.SC2Bank`, `author` the handle of the map's publisher (the name of the folder the bank
+/// is in) and `player` the handle of the player the bank belongs to.
+pub fn sign(bank_name: &str, author: &Handle, player: &Handle, document: &XmlBank) -> String {
+    signature_of(bank_name, author, player, &document.sections)
+}
+
+fn signature_of(bank_name: &str, author: &Handle, player: &Handle, sections: &[Section]) -> String {
     let mut hasher = Sha1::new();
 
-    hasher.update(HANDLE_PREFIX_NA);
-    hasher.update(&AUTHOR_HANDLE_NA.to_string());
-    hasher.update(HANDLE_PREFIX_NA);
-    hasher.update(&player_handle.to_string());
-    hasher.update(BANK_FILENAME);
+    hasher.update(author.to_string());
+    hasher.update(player.to_string());
+    hasher.update(bank_name);
 
+    let mut sections = sections.iter().collect::<Vec<_>>();
     sections.sort_by(|a, b| a.name.cmp(&b.name));
 
-    for mut section in sections {
-        hasher.update(section.name);
-        section.keys.sort_by(|a, b| a.name.cmp(&b.name));
-        for key in section.keys {
-            hasher.update(key.name);
-            hasher.update("Value");
-            hasher.update("string");
-            hasher.update(key.value.string);
+    for section in sections {
+        hasher.update(&section.name);
+        let mut keys = section.keys.iter().collect::<Vec<_>>();
+        keys.sort_by(|a, b| a.name.cmp(&b.name));
+        for key in keys {
+            hasher.update(&key.name);
+            for (value_type, value) in key.value.attributes() {
+                hasher.update("Value");
+                hasher.update(value_type);
+                hasher.update(value);
+            }
         }
     }
 
@@ -50,130 +97,644 @@ fn compute_signature(player_handle: usize, mut sections: Vec<Section>) -> String
     base16ct::upper::encode_string(&hash)
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-struct Value {
-    #[serde(rename = "@string")]
-    string: String,
+/// The types a bank value can have, which SC2 writes as the name of the value's attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    String,
+    Int,
+    /// A fixed point number, like `1.5`
+    Fixed,
+    /// A boolean, written as `0` or `1`
+    Flag,
+    Text,
+    /// A position on the map, like `12.5,40`
+    Point,
+    Unit,
+}
+impl ValueType {
+    pub const ALL: [ValueType; 7] = [
+        ValueType::String,
+        ValueType::Int,
+        ValueType::Fixed,
+        ValueType::Flag,
+        ValueType::Text,
+        ValueType::Point,
+        ValueType::Unit,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ValueType::String => "string",
+            ValueType::Int => "int",
+            ValueType::Fixed => "fixed",
+            ValueType::Flag => "flag",
+            ValueType::Text => "text",
+            ValueType::Point => "point",
+            ValueType::Unit => "unit",
+        }
+    }
+}
+
+/// The value of a [`Key`]. SC2 gives each value a single attribute named after its type, e.g.
+/// `<Value int="5"/>`, but every attribute a bank has is kept, in order, including ones of types
+/// that are not known here.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Value {
+    /// The name and value of each attribute
+    attributes: Vec<(String, String)>,
 }
+impl Value {
+    pub fn new(value_type: ValueType, value: String) -> Self {
+        Self {
+            attributes: vec![(value_type.name().to_string(), value)],
+        }
+    }
+
+    pub fn string(value: String) -> Self {
+        Self::new(ValueType::String, value)
+    }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-struct Key {
+    /// The value as the given type, if it has one of that type
+    pub fn get(&self, value_type: ValueType) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(name, _)| name == value_type.name())
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Every attribute of the value, i.e. the name of each type it has along with the value as
+    /// that type
+    pub fn attributes(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.attributes
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+impl fmt::Display for Value {
+    /// Writes string values as they are, and any other type as `<type>:<value>`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let attributes = self.attributes().collect::<Vec<_>>();
+        if let [("string", value)] = attributes[..] {
+            return f.write_str(value);
+        }
+        for (i, (name, value)) in attributes.into_iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{name}:{value}")?;
+        }
+        Ok(())
+    }
+}
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ValueVisitor;
+        impl<'de> Visitor<'de> for ValueVisitor {
+            type Value = Value;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a value with attributes")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+                let mut attributes = Vec::new();
+                while let Some((name, value)) = map.next_entry::<String, String>()? {
+                    // Attributes are the only part of a value that is written back, so anything
+                    // else would be lost
+                    let Some(name) = name.strip_prefix('@') else {
+                        return Err(de::Error::custom(format!(
+                            "a value can only have attributes, but has `{name}`"
+                        )));
+                    };
+                    attributes.push((name.to_string(), value));
+                }
+                Ok(Value { attributes })
+            }
+        }
+        deserializer.deserialize_map(ValueVisitor)
+    }
+}
+
+/// A named value in a [`Section`]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Key {
     #[serde(rename = "@name")]
     name: String,
 
     #[serde(rename = "Value")]
     value: Value,
 }
+impl Key {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+}
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-struct Section {
+/// A named group of keys, kept in the order they appear in the file
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Section {
     #[serde(rename = "@name")]
     name: String,
 
-    #[serde(rename = "Key")]
+    #[serde(rename = "Key", default)]
     keys: Vec<Key>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+impl Section {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn keys(&self) -> &[Key] {
+        &self.keys
+    }
+
+    pub fn key(&self, name: &str) -> Option<&Value> {
+        self.keys
+            .iter()
+            .find(|key| key.name == name)
+            .map(|key| &key.value)
+    }
+
+    /// The key's value if it is there, which has to be a string
+    fn string_key(&self, name: &str) -> Result<Option<&str>> {
+        let Some(value) = self.key(name) else {
+            return Ok(None);
+        };
+        match value.get(ValueType::String) {
+            Some(string) => Ok(Some(string)),
+            None => Err(Error::UnexpectedValueType {
+                expected: ValueType::String.name(),
+                found: value.to_string(),
+            }
+            .in_key(&self.name, name)),
+        }
+    }
+
+    /// Changes the value of a key, or adds it if it is missing. New keys go before the first key
+    /// that sorts after them, which keeps them in order in banks written by SC2.
+    pub fn set_key(&mut self, name: &str, value: Value) {
+        if let Some(key) = self.keys.iter_mut().find(|key| key.name == name) {
+            key.value = value;
+            return;
+        }
+        let index = self
+            .keys
+            .iter()
+            .position(|key| key.name.as_str() > name)
+            .unwrap_or(self.keys.len());
+        self.keys.insert(
+            index,
+            Key {
+                name: name.to_string(),
+                value,
+            },
+        );
+    }
+
+    pub fn remove_key(&mut self, name: &str) {
+        self.keys.retain(|key| key.name != name);
+    }
+
+    fn required_key(&self, name: &str) -> Result<&str> {
+        self.string_key(name)?.ok_or_else(|| Error::MissingKey {
+            section: self.name.clone(),
+            key: name.to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 struct Signature {
     #[serde(rename = "@value")]
     value: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// A bank file as stored on disk. Every section and key is kept, in order, whether or not
+/// [`Bank`] knows what it is for.
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename = "Bank")]
 pub struct XmlBank {
     #[serde(rename = "@version")]
     version: usize,
 
-    #[serde(rename = "Section")]
-    section: (Section, Section),
+    #[serde(rename = "Section", default)]
+    sections: Vec<Section>,
 
     #[serde(rename = "Signature")]
     signature: Signature,
+
+    #[serde(skip)]
+    source: Option<Box<Source>>,
 }
+
+/// The text a bank was parsed from, along with what it parsed to. As long as the bank still holds
+/// the same contents, it is written back as this text, so that a bank that was loaded and saved
+/// without changes stays byte for byte the same whatever quirks of formatting it has.
+#[derive(Debug, Clone)]
+struct Source {
+    text: String,
+    version: usize,
+    sections: Vec<Section>,
+    signature: Signature,
+}
+impl Source {
+    fn matches(&self, bank: &XmlBank) -> bool {
+        self.version == bank.version
+            && self.sections == bank.sections
+            && self.signature == bank.signature
+    }
+}
+
+/// The outcome of one of the checks done by [`XmlBank::verify`]
+#[derive(Debug)]
+pub struct Check {
+    pub description: &'static str,
+    pub passed: bool,
+    pub details: String,
+}
+
 impl XmlBank {
-    pub fn to_correctly_formatted_bank(self) -> String {
-        let prefix_string = r#"<?xml version="1.0" encoding="utf-8"?>"#.to_string() + "\n";
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
 
-        let mut buffer = String::new();
-        let mut ser = Serializer::new(&mut buffer);
-        ser.indent(' ', 4);
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| section.name == name)
+    }
 
-        self.serialize(ser).unwrap();
+    /// The section with the given name, which is added at the end of the bank if it is missing
+    pub fn section_mut(&mut self, name: &str) -> &mut Section {
+        let index = match self
+            .sections
+            .iter()
+            .position(|section| section.name == name)
+        {
+            Some(index) => index,
+            None => {
+                self.sections.push(Section {
+                    name: name.to_string(),
+                    keys: Vec::new(),
+                });
+                self.sections.len() - 1
+            }
+        };
+        &mut self.sections[index]
+    }
 
-        prefix_string + &buffer + "\n"
+    fn required_section(&self, name: &str) -> Result<&Section> {
+        self.section(name)
+            .ok_or_else(|| Error::MissingSection(name.to_string()))
     }
-}
-impl From<Bank> for XmlBank {
-    fn from(value: Bank) -> Self {
-        let mut unit_section: Section = Section {
-            name: "unit".to_string(),
-            keys: Vec::new(),
+
+    /// Recomputes the signature and checksums that SC2 uses to validate the bank, and compares them
+    /// with the ones stored in it. The signature depends on the region the bank is from, and on the
+    /// player's handle. `player_handle` is the handle SC2 checks the bank against, i.e. the one in
+    /// the path of the folder it is stored in; when it is given, the bank is signed with it and the
+    /// handle derived from the camera key has to match it. A camera or unit info key that cannot
+    /// be decoded fails its check instead of returning an error, so only an account or unit that
+    /// cannot be decoded at all is an error.
+    pub fn verify(&self, region: &BankRegion, player_handle: Option<usize>) -> Result<Vec<Check>> {
+        // The fallback handle is never used, since the handle only counts if it could be derived
+        let (bank, camera_error) = Bank::decode_with(self.clone(), *region, Some(0))?;
+        let unit_section = self.required_section("unit")?;
+        let account_section = self.required_section("account")?;
+
+        let derived_player_handle = match camera_error {
+            None => Ok(bank.player_handle),
+            Some(e) => Err(e),
         };
-        for unit_index in 1..=8 {
-            if let Some(unit) = &value.units[unit_index - 1] {
-                let unit_string = unit.to_data();
-                unit_section.keys.push(Key {
-                    name: format!("0{unit_index}"),
-                    value: Value {
-                        string: unit_string,
-                    },
-                });
+        let player_handle_check = Check {
+            description: "Player handle derived from the camera key matches the bank's folder",
+            passed: derived_player_handle
+                .as_ref()
+                .is_ok_and(|derived| player_handle.is_none_or(|handle| handle == *derived)),
+            details: match (&derived_player_handle, player_handle) {
+                (Ok(derived), Some(handle)) => format!(
+                    "derived {}, folder {}",
+                    region.player_handle(*derived),
+                    region.player_handle(handle)
+                ),
+                (Ok(derived), None) => format!("derived {}", region.player_handle(*derived)),
+                (Err(e), _) => e.to_string(),
+            },
+        };
+
+        let signature_check = match player_handle.or(derived_player_handle.ok()) {
+            Some(player_handle) => {
+                let expected_signature = compute_signature(region, player_handle, &self.sections);
+                Check {
+                    description: "Signature matches the bank contents",
+                    passed: expected_signature == self.signature.value,
+                    details: format!(
+                        "stored {}, computed {expected_signature}",
+                        self.signature.value
+                    ),
+                }
             }
-        }
-        unit_section.keys.push(Key {
-            name: format!("info"),
-            value: Value {
-                string: value.generate_unit_slots_data(),
+            None => Check {
+                description: "Signature matches the bank contents",
+                passed: false,
+                details: format!(
+                    "stored {}, but it cannot be computed without the player handle",
+                    self.signature.value
+                ),
             },
-        });
+        };
 
-        let account_info = value.account.to_data();
-        let account_camera = value.generate_camera_checksum();
-        let account_section = Section {
-            name: "account".to_string(),
-            keys: vec![
-                Key {
-                    name: "camera".to_string(),
-                    value: Value {
-                        string: account_camera,
-                    },
-                },
-                Key {
-                    name: "info".to_string(),
-                    value: Value {
-                        string: account_info,
-                    },
-                },
-            ],
+        let stored_account_checksum = account_section
+            .required_key("camera")
+            .and_then(|camera| decode_camera_checksum(camera.to_string()))
+            .map_err(|e| e.in_key("account", "camera"));
+        let account_checksum_check = Check {
+            description: "Camera key's account checksum matches the account info",
+            passed: stored_account_checksum
+                .as_ref()
+                .is_ok_and(|(stored, _)| *stored == bank.account.checksum()),
+            details: match stored_account_checksum {
+                Ok((stored, _)) => format!("stored {stored}, computed {}", bank.account.checksum()),
+                Err(e) => e.to_string(),
+            },
         };
 
-        let signature = Signature {
-            value: compute_signature(
-                value.player_handle,
-                vec![unit_section.clone(), account_section.clone()],
-            ),
+        let flagged = unit_section
+            .required_key("info")
+            .and_then(|info| decode_unit_slots_data(info.to_string()))
+            .map_err(|e| e.in_key("unit", "info"));
+        let present: [bool; 8] =
+            std::array::from_fn(|i| unit_section.key(&format!("0{}", i + 1)).is_some());
+        let slots = |occupied: [bool; 8]| (0..8).filter(|i| occupied[*i]).collect::<Vec<_>>();
+        let occupancy_check = Check {
+            description: "Unit info flags match the unit keys present",
+            passed: flagged.as_ref().is_ok_and(|flagged| *flagged == present),
+            details: match flagged {
+                Ok(flagged) => format!(
+                    "flagged slots {:?}, slots with a unit key {:?}",
+                    slots(flagged),
+                    slots(present)
+                ),
+                Err(e) => e.to_string(),
+            },
         };
 
-        XmlBank {
-            version: 1,
-            section: (unit_section, account_section),
-            signature,
+        Ok(vec![
+            player_handle_check,
+            signature_check,
+            account_checksum_check,
+            occupancy_check,
+        ])
+    }
+
+    /// Checks that `backup` can replace this bank: it has to pass [`XmlBank::verify`] and belong to
+    /// the same player as this bank. That player is `player_handle` when it is given, i.e. the one
+    /// in the path of the bank's folder, and is otherwise derived from this bank. Nothing else of
+    /// this bank has to decode, since a corrupt bank is the main reason to restore a backup. If the
+    /// player cannot be derived, the error is returned and the backup is only verified on its own.
+    pub fn check_restore(
+        &self,
+        backup: &XmlBank,
+        region: &BankRegion,
+        player_handle: Option<usize>,
+    ) -> Result<Option<Error>> {
+        let failed_checks = backup
+            .verify(region, None)?
+            .into_iter()
+            .filter(|check| !check.passed)
+            .map(|check| format!("{} ({})", check.description, check.details))
+            .collect::<Vec<_>>();
+        if !failed_checks.is_empty() {
+            return Err(Error::BackupFailedVerification(failed_checks.join(", ")));
+        }
+
+        let player_handle = match player_handle {
+            Some(player_handle) => player_handle,
+            None => match Bank::decode(self.clone(), *region) {
+                Ok(bank) => bank.player_handle,
+                Err(e) => return Ok(Some(e)),
+            },
+        };
+        let backup_player_handle = Bank::decode(backup.clone(), *region)?.player_handle;
+        if backup_player_handle != player_handle {
+            return Err(Error::PlayerMismatch {
+                backup: region.player_handle(backup_player_handle),
+                bank: region.player_handle(player_handle),
+            });
+        }
+        Ok(None)
+    }
+
+    /// Every key in the bank as `section/key`, along with its raw value
+    pub fn values(&self) -> Vec<(String, String)> {
+        self.sections
+            .iter()
+            .flat_map(|section| {
+                section.keys.iter().map(|key| {
+                    (
+                        format!("{}/{}", section.name, key.name),
+                        key.value.to_string(),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// The `section/key` names whose raw values differ between two banks, with the value in each
+    /// (or `None` if the key is missing from that bank)
+    pub fn changed_values(&self, other: &XmlBank) -> Vec<(String, Option<String>, Option<String>)> {
+        let before = self.values();
+        let after = other.values();
+        let mut keys = before.iter().map(|(key, _)| key).collect::<Vec<_>>();
+        for (key, _) in &after {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        let find = |values: &[(String, String)], key: &str| {
+            values
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, value)| value.clone())
+        };
+        keys.into_iter()
+            .map(|key| (key.clone(), find(&before, key), find(&after, key)))
+            .filter(|(_, before, after)| before != after)
+            .collect()
+    }
+
+    pub fn signature(&self) -> &str {
+        &self.signature.value
+    }
+
+    /// Replaces the stored signature, e.g. with one from [`sign`]
+    pub fn set_signature(&mut self, signature: String) {
+        self.signature.value = signature;
+    }
+
+    /// Writes the bank the way SC2 does. A bank that has not changed since it was parsed is
+    /// written exactly as it was read, and an edited one keeps the line endings it was read with.
+    pub fn to_correctly_formatted_bank(&self) -> Result<String> {
+        if let Some(source) = self.source.as_ref().filter(|source| source.matches(self)) {
+            return Ok(source.text.clone());
+        }
+
+        let raw_bank =
+            String::from_utf8(self.write().map_err(DeError::from)?).map_err(DeError::from)? + "\n";
+        match &self.source {
+            Some(source) if source.text.contains("\r\n") => Ok(raw_bank.replace('\n', "\r\n")),
+            _ => Ok(raw_bank),
         }
     }
 }
+impl XmlBank {
+    fn write(&self) -> quick_xml::Result<Vec<u8>> {
+        let mut writer = Writer::new_with_indent(Vec::new(), b' ', 4);
+        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("utf-8"), None)))?;
+        let version = self.version.to_string();
+        writer.write_event(Event::Start(
+            BytesStart::new("Bank").with_attributes([("version", version.as_str())]),
+        ))?;
+        for section in &self.sections {
+            let start =
+                BytesStart::new("Section").with_attributes([("name", section.name.as_str())]);
+            if section.keys.is_empty() {
+                writer.write_event(Event::Empty(start))?;
+                continue;
+            }
+            writer.write_event(Event::Start(start))?;
+            for key in &section.keys {
+                writer.write_event(Event::Start(
+                    BytesStart::new("Key").with_attributes([("name", key.name.as_str())]),
+                ))?;
+                writer.write_event(Event::Empty(
+                    BytesStart::new("Value").with_attributes(key.value.attributes()),
+                ))?;
+                writer.write_event(Event::End(BytesEnd::new("Key")))?;
+            }
+            writer.write_event(Event::End(BytesEnd::new("Section")))?;
+        }
+        writer.write_event(Event::Empty(
+            BytesStart::new("Signature")
+                .with_attributes([("value", self.signature.value.as_str())]),
+        ))?;
+        writer.write_event(Event::End(BytesEnd::new("Bank")))?;
+        Ok(writer.into_inner())
+    }
+}
+impl FromStr for XmlBank {
+    type Err = Error;
+
+    fn from_str(raw_bank: &str) -> Result<Self> {
+        let mut bank: XmlBank = quick_xml::de::from_str(raw_bank)?;
+        bank.source = Some(Box::new(Source {
+            text: raw_bank.to_string(),
+            version: bank.version,
+            sections: bank.sections.clone(),
+            signature: bank.signature.clone(),
+        }));
+        Ok(bank)
+    }
+}
+impl TryFrom<Bank> for XmlBank {
+    type Error = Error;
+
+    /// Writes the bank's units and account into the document it was decoded from, leaving any
+    /// other sections and keys as they were, and signs it again
+    fn try_from(value: Bank) -> Result<Self> {
+        // The records are encoded first, so that a field out of range is blamed on its own key
+        // rather than on the checksums it adds to
+        let unit_data = value
+            .units
+            .iter()
+            .enumerate()
+            .map(|(unit_index, unit)| {
+                let key = format!("0{}", unit_index + 1);
+                let data = unit
+                    .as_ref()
+                    .map(|unit| unit.to_data().map_err(|e| e.in_key("unit", &key)))
+                    .transpose()?;
+                Ok((key, data))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let account_info = value
+            .account
+            .to_data()
+            .map_err(|e| e.in_key("account", "info"))?;
+        let unit_slots_data = value.generate_unit_slots_data()?;
+        let account_camera = value
+            .generate_camera_checksum()
+            .map_err(|e| e.in_key("account", "camera"))?;
+
+        let mut document = value.document;
+
+        let unit_section = document.section_mut("unit");
+        for (key, data) in unit_data {
+            match data {
+                Some(unit_string) => unit_section.set_key(&key, Value::string(unit_string)),
+                None => unit_section.remove_key(&key),
+            }
+        }
+        unit_section.set_key("info", Value::string(unit_slots_data));
+
+        let account_section = document.section_mut("account");
+        account_section.set_key("camera", Value::string(account_camera));
+        account_section.set_key("info", Value::string(account_info));
+
+        document.signature.value =
+            compute_signature(&value.region, value.player_handle, &document.sections);
+        Ok(document)
+    }
+}
 
 #[derive(Debug)]
 pub struct Bank {
     pub units: [Option<Runling>; 8],
     pub account: Account,
     pub player_handle: usize,
+    /// The region the bank is signed for when it is converted back into an [`XmlBank`]
+    pub region: BankRegion,
+    /// The bank this was decoded from, which keeps the sections and keys that are not decoded
+    document: XmlBank,
 }
 impl Bank {
-    pub fn generate_camera_checksum(&self) -> String {
-        let mut data = BigInt::ZERO;
-        store_int(&mut data, self.account.checksum(), 99000000);
+    /// A bank that was not decoded from a file. Its document starts out with empty `unit` and
+    /// `account` sections, which are filled in and signed when it is converted into an [`XmlBank`].
+    pub fn new(
+        units: [Option<Runling>; 8],
+        account: Account,
+        player_handle: usize,
+        region: BankRegion,
+    ) -> Self {
+        let mut document = XmlBank {
+            version: 1,
+            sections: Vec::new(),
+            signature: Signature {
+                value: String::new(),
+            },
+            source: None,
+        };
+        document.section_mut("unit");
+        document.section_mut("account");
+        Self {
+            units,
+            account,
+            player_handle,
+            region,
+            document,
+        }
+    }
+
+    /// The bank this was decoded from, as it was before any changes
+    pub fn document(&self) -> &XmlBank {
+        &self.document
+    }
+
+    pub fn generate_camera_checksum(&self) -> Result<String> {
+        let mut data = BigUint::ZERO;
+        store_int(&mut data, self.account.checksum(), 99000000)?;
         store_int(
             &mut data,
             self.units
@@ -183,12 +744,12 @@ impl Bank {
                 .sum::<usize>()
                 + self.player_handle,
             98000000,
-        );
+        )?;
         starcode::encrypt(starcode::compress(data))
     }
 
-    pub fn generate_unit_slots_data(&self) -> String {
-        let mut data = BigInt::ZERO;
+    pub fn generate_unit_slots_data(&self) -> Result<String> {
+        let mut data = BigUint::ZERO;
         for unit_index in 0..8 {
             let val = if self.units[unit_index].is_some() {
                 1
@@ -196,60 +757,652 @@ impl Bank {
                 0
             };
             let max_val = 425 + unit_index;
-            store_int(&mut data, val, max_val);
+            store_int(&mut data, val, max_val)?;
         }
         starcode::encrypt(starcode::compress(data))
     }
 }
-impl From<XmlBank> for Bank {
-    fn from(value: XmlBank) -> Self {
-        let (unit_section, account_section) = if value.section.0.name == "unit" {
-            value.section
-        } else {
-            (value.section.1, value.section.0)
+impl Bank {
+    /// Decodes the units and account of a bank from `region`. Banks decode the same in every
+    /// region, but the region is what the bank is signed for again once it is edited.
+    pub fn decode(value: XmlBank, region: BankRegion) -> Result<Self> {
+        Ok(Self::decode_with(value, region, None)?.0)
+    }
+
+    /// Decodes the bank like [`Bank::decode`], except that if the player handle cannot be derived
+    /// because the camera key is missing or corrupt, `player_handle` is used instead. The error
+    /// from the camera key is returned alongside the bank when that happens.
+    pub fn decode_with_fallback(
+        value: XmlBank,
+        region: BankRegion,
+        player_handle: usize,
+    ) -> Result<(Self, Option<Error>)> {
+        Self::decode_with(value, region, Some(player_handle))
+    }
+
+    fn decode_with(
+        value: XmlBank,
+        region: BankRegion,
+        fallback_player_handle: Option<usize>,
+    ) -> Result<(Self, Option<Error>)> {
+        let unit_section = value.required_section("unit")?;
+        let account_section = value.required_section("account")?;
+
+        let account = Account::from_data(account_section.required_key("info")?.to_string())
+            .map_err(|e| e.in_key("account", "info"))?;
+
+        let mut units: [Option<Runling>; 8] = [None, None, None, None, None, None, None, None];
+        for runling_index in 1..=8 {
+            let key = format!("0{runling_index}");
+            if let Some(runling_info) = unit_section.string_key(&key)? {
+                units[runling_index - 1] = Some(
+                    Runling::from_data(runling_info.to_string())
+                        .map_err(|e| e.in_key("unit", &key))?,
+                );
+            }
+        }
+
+        let derived_player_handle = account_section.required_key("camera").and_then(|checksum| {
+            derive_player_handle(checksum.to_string(), &units)
+                .map_err(|e| e.in_key("account", "camera"))
+        });
+        let (player_handle, camera_error) = match (derived_player_handle, fallback_player_handle) {
+            (Ok(player_handle), _) => (player_handle, None),
+            (Err(e), Some(fallback)) => (fallback, Some(e)),
+            (Err(e), None) => return Err(e),
         };
-        assert_eq!(unit_section.name, "unit");
-        assert_eq!(account_section.name, "account");
 
-        let checksum = account_section
-            .keys
-            .iter()
-            .find(|key| key.name == "camera")
+        let bank = Self {
+            units,
+            account,
+            player_handle,
+            region,
+            document: value,
+        };
+        Ok((bank, camera_error))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::{self, read_to_string};
+
+    use sha1::{Digest, Sha1};
+
+    use crate::{
+        bank::{sign, Bank, Value, ValueType, XmlBank, BANK_NAME},
+        error::Error,
+        region::{BankRegion, Handle, Region},
+    };
+
+    fn load_test_bank() -> XmlBank {
+        read_to_string("./data/RunlingRun004.SC2Bank")
+            .unwrap()
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn unmodified_bank_keeps_signature() {
+        let xml_bank = load_test_bank();
+        let signature = xml_bank.signature.value.clone();
+
+        let resaved =
+            XmlBank::try_from(Bank::decode(xml_bank, BankRegion::default()).unwrap()).unwrap();
+
+        assert_eq!(resaved.signature.value, signature);
+    }
+
+    #[test]
+    fn signed_for_the_decoded_region() {
+        let xml_bank = load_test_bank();
+
+        // No bank from another region is available, so only the Americas signature can be checked
+        // against one that SC2 wrote. In the other regions, with a made-up author, this only shows
+        // that the bank is signed for the region it was decoded for
+        for region in Region::ALL {
+            let author = Handle {
+                region,
+                realm: 1,
+                id: 417073,
+            };
+            let bank_region = BankRegion::with_author(author);
+            let bank = Bank::decode(xml_bank.clone(), bank_region).unwrap();
+            let player = bank_region.player_handle(bank.player_handle);
+            let signed = XmlBank::try_from(bank).unwrap();
+
+            assert_eq!(
+                signed.signature(),
+                sign(BANK_NAME, &author, &player, &xml_bank),
+                "{region:?}"
+            );
+            assert_eq!(
+                signed.signature() == xml_bank.signature(),
+                region == Region::Americas,
+                "{region:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn sign_matches_runling_run_signature() {
+        let mut xml_bank = load_test_bank();
+        let signature = xml_bank.signature().to_string();
+        xml_bank.set_signature(String::new());
+
+        let author = "1-S2-1-417073".parse().unwrap();
+        let player = "1-S2-1-6930628".parse().unwrap();
+        assert_eq!(sign(BANK_NAME, &author, &player, &xml_bank), signature);
+        assert_ne!(
+            sign("RunlingRun003", &author, &player, &xml_bank),
+            signature
+        );
+    }
+
+    #[test]
+    fn verify_test_bank() {
+        let checks = load_test_bank()
+            .verify(&BankRegion::default(), None)
+            .unwrap();
+
+        assert_eq!(checks.len(), 4);
+        assert!(checks.iter().all(|check| check.passed), "{checks:#?}");
+    }
+
+    #[test]
+    fn verify_detects_tampering() {
+        let mut xml_bank = load_test_bank();
+        xml_bank.signature.value = "0".repeat(40);
+        xml_bank.section_mut("unit").remove_key("02");
+
+        let checks = xml_bank.verify(&BankRegion::default(), None).unwrap();
+
+        assert!(checks[0].passed);
+        assert!(!checks[1].passed);
+        assert!(checks[2].passed);
+        assert!(!checks[3].passed);
+    }
+
+    #[test]
+    fn verify_checks_the_folder_player_handle() {
+        let xml_bank = load_test_bank();
+        let player_handle = Bank::decode(xml_bank.clone(), BankRegion::default())
             .unwrap()
-            .value
-            .string
-            .clone();
+            .player_handle;
 
-        let account = Account::from_data(
-            account_section
-                .keys
-                .iter()
-                .find(|key| key.name == "info")
+        let checks = xml_bank
+            .verify(&BankRegion::default(), Some(player_handle))
+            .unwrap();
+        assert!(checks.iter().all(|check| check.passed), "{checks:#?}");
+
+        // A bank in another player's folder is signed for that player, so it does not match
+        let checks = xml_bank
+            .verify(&BankRegion::default(), Some(player_handle + 1))
+            .unwrap();
+        assert!(!checks[0].passed);
+        assert!(!checks[1].passed);
+        assert!(checks[2].passed);
+    }
+
+    #[test]
+    fn verify_reports_undecodable_checksums() {
+        let raw_bank = read_to_string("./data/RunlingRun004.SC2Bank").unwrap();
+        let mut xml_bank: XmlBank = raw_bank
+            .replace(r#"<Key name="camera">"#, r#"<Key name="lens">"#)
+            .parse()
+            .unwrap();
+        xml_bank
+            .section_mut("unit")
+            .set_key("info", Value::string("\\".to_string()));
+
+        let checks = xml_bank.verify(&BankRegion::default(), None).unwrap();
+
+        assert_eq!(checks.len(), 4);
+        assert!(checks.iter().all(|check| !check.passed), "{checks:#?}");
+        assert_eq!(
+            checks[0].details,
+            "the `account` section has no `camera` key"
+        );
+        assert_eq!(
+            checks[3].details,
+            "`unit/info`: `\\` is not a character that can appear in starcode"
+        );
+    }
+
+    #[test]
+    fn restore_over_undecodable_bank() {
+        let raw_bank = read_to_string("./data/RunlingRun004.SC2Bank").unwrap();
+        let backup = load_test_bank();
+        let region = BankRegion::default();
+        let corrupt = raw_bank
+            .replace("$ jF*y^IFgl4", "$ jF*y^IFgl\\")
+            .parse::<XmlBank>()
+            .unwrap();
+        assert!(Bank::decode(corrupt.clone(), BankRegion::default()).is_err());
+
+        assert!(corrupt
+            .check_restore(&backup, &region, Some(6930628))
+            .unwrap()
+            .is_none());
+        let error = corrupt
+            .check_restore(&backup, &region, Some(1))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the backup belongs to player 1-S2-1-6930628, but the bank belongs to player 1-S2-1-1"
+        );
+        // Without the folder's handle, whose bank it is cannot be told, so only the backup is checked
+        let player_error = corrupt
+            .check_restore(&backup, &region, None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            player_error.to_string(),
+            "`account/info`: `\\` is not a character that can appear in starcode"
+        );
+
+        let mut tampered = backup.clone();
+        tampered.set_signature("0".repeat(40));
+        assert!(matches!(
+            corrupt.check_restore(&tampered, &region, Some(6930628)),
+            Err(Error::BackupFailedVerification(_))
+        ));
+    }
+
+    #[test]
+    fn new_bank_matches_decoded_bank() {
+        let xml_bank = load_test_bank();
+        let decoded = Bank::decode(xml_bank.clone(), BankRegion::default()).unwrap();
+
+        let bank = Bank::new(
+            decoded.units.clone(),
+            decoded.account.clone(),
+            decoded.player_handle,
+            BankRegion::default(),
+        );
+        let built = XmlBank::try_from(bank).unwrap();
+
+        // The test bank holds nothing but the decoded keys, so it is rebuilt exactly
+        assert_eq!(built.signature(), xml_bank.signature());
+        assert_eq!(built.values(), xml_bank.values());
+        let checks = built.verify(&BankRegion::default(), None).unwrap();
+        assert!(checks.iter().all(|check| check.passed), "{checks:#?}");
+        let raw_bank = built.to_correctly_formatted_bank().unwrap();
+        assert_eq!(
+            Bank::decode(raw_bank.parse::<XmlBank>().unwrap(), BankRegion::default())
                 .unwrap()
-                .value
-                .string
-                .clone(),
+                .units,
+            decoded.units
         );
+    }
 
-        let mut units: [Option<Runling>; 8] = [None, None, None, None, None, None, None, None];
-        for runling_index in 1..=8 {
-            let runling_info = unit_section
-                .keys
+    #[test]
+    fn encode_errors_name_the_field() {
+        let mut bank = Bank::decode(load_test_bank(), BankRegion::default()).unwrap();
+        bank.units[2].as_mut().unwrap().runling_level = 400000;
+        let error = XmlBank::try_from(bank).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`unit/03`: runling_level can be at most 370000, but is 400000"
+        );
+    }
+
+    #[test]
+    fn decode_errors_name_the_key() {
+        let raw_bank = read_to_string("./data/RunlingRun004.SC2Bank").unwrap();
+
+        let bad_unit = raw_bank.replace("6Zb?$-s9Bq3f O%`(vvz *4i", "6Zb?$-s9Bq3f O%`(vvz *4\\");
+        let error =
+            Bank::decode(bad_unit.parse::<XmlBank>().unwrap(), BankRegion::default()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`unit/03`: `\\` is not a character that can appear in starcode"
+        );
+
+        let no_camera = raw_bank.replace(r#"<Key name="camera">"#, r#"<Key name="lens">"#);
+        let error =
+            Bank::decode(no_camera.parse::<XmlBank>().unwrap(), BankRegion::default()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the `account` section has no `camera` key"
+        );
+    }
+
+    #[test]
+    fn fallback_player_handle() {
+        let raw_bank = read_to_string("./data/RunlingRun004.SC2Bank").unwrap();
+        let corrupt_camera = raw_bank
+            .replace(")`1Z16g", ")`1Z\\6g")
+            .parse::<XmlBank>()
+            .unwrap();
+
+        assert!(Bank::decode(corrupt_camera.clone(), BankRegion::default()).is_err());
+        let (bank, camera_error) =
+            Bank::decode_with_fallback(corrupt_camera, BankRegion::default(), 6930628).unwrap();
+        assert_eq!(bank.player_handle, 6930628);
+        assert_eq!(
+            camera_error.unwrap().to_string(),
+            "`account/camera`: `\\` is not a character that can appear in starcode"
+        );
+
+        let (bank, camera_error) =
+            Bank::decode_with_fallback(load_test_bank(), BankRegion::default(), 1).unwrap();
+        assert_eq!(bank.player_handle, 6930628);
+        assert!(camera_error.is_none());
+    }
+
+    #[test]
+    fn slot_changes_regenerate_info_and_camera() {
+        let mut bank = Bank::decode(load_test_bank(), BankRegion::default()).unwrap();
+        let player_handle = bank.player_handle;
+
+        bank.units.swap(0, 7);
+        bank.units[6] = bank.units[1].take();
+        bank.units[2] = None;
+        bank.units[3] = bank.units[4].clone();
+
+        let units = bank.units.clone();
+        let reloaded =
+            Bank::decode(XmlBank::try_from(bank).unwrap(), BankRegion::default()).unwrap();
+
+        assert_eq!(reloaded.units, units);
+        assert_eq!(reloaded.player_handle, player_handle);
+    }
+
+    #[test]
+    fn unknown_sections_and_keys_are_kept() {
+        let raw_bank = read_to_string("./data/RunlingRun004.SC2Bank")
+            .unwrap()
+            .replace(
+                r#"<Key name="info">"#,
+                r#"<Key name="future"><Value string="kept"/></Key><Key name="info">"#,
+            )
+            .replace(
+                "</Section>\n    <Signature",
+                r#"</Section><Section name="stats"><Key name="runs"><Value string="12"/></Key></Section><Signature"#,
+            );
+        let xml_bank = raw_bank.parse::<XmlBank>().unwrap();
+        let section_names = |xml_bank: &XmlBank| {
+            xml_bank
+                .sections()
                 .iter()
-                .find(|key| key.name == format!("0{runling_index}"));
+                .map(|section| section.name().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(section_names(&xml_bank), ["unit", "account", "stats"]);
+
+        let mut bank = Bank::decode(xml_bank.clone(), BankRegion::default()).unwrap();
+        bank.units[6] = bank.units[0].clone();
+        bank.units[1] = None;
+        let resaved = XmlBank::try_from(bank).unwrap();
+
+        assert_eq!(section_names(&resaved), ["unit", "account", "stats"]);
+        let unit_keys = resaved.section("unit").unwrap().keys();
+        assert_eq!(
+            unit_keys.iter().map(|key| key.name()).collect::<Vec<_>>(),
+            ["01", "03", "04", "05", "06", "07", "future", "info"]
+        );
+        let value = |section: &str, key: &str| {
+            resaved
+                .section(section)
+                .and_then(|section| section.key(key))
+                .map(|value| value.to_string())
+        };
+        assert_eq!(value("unit", "future").as_deref(), Some("kept"));
+        assert_eq!(value("account", "future").as_deref(), Some("kept"));
+        assert_eq!(value("stats", "runs").as_deref(), Some("12"));
+
+        // The signature covers the keys that are not decoded too
+        let checks = resaved.verify(&BankRegion::default(), None).unwrap();
+        assert!(checks.iter().all(|check| check.passed), "{checks:#?}");
+        assert_ne!(resaved.signature(), xml_bank.signature());
+    }
 
-            if let Some(runling_info) = runling_info {
-                units[runling_index - 1] =
-                    Some(Runling::from_data(runling_info.value.string.clone()));
+    const MIXED_TYPE_BANK: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<Bank version="1">
+    <Section name="progress">
+        <Key name="level">
+            <Value int="12"/>
+        </Key>
+        <Key name="speed">
+            <Value fixed="1.5"/>
+        </Key>
+        <Key name="done">
+            <Value flag="1"/>
+        </Key>
+        <Key name="greeting">
+            <Value text="Hello there"/>
+        </Key>
+        <Key name="spawn">
+            <Value point="12.5,40"/>
+        </Key>
+        <Key name="name">
+            <Value string="runner"/>
+        </Key>
+    </Section>
+    <Signature value="0000000000000000000000000000000000000000"/>
+</Bank>
+"#;
+
+    #[test]
+    fn mixed_type_values() {
+        let xml_bank = MIXED_TYPE_BANK.parse::<XmlBank>().unwrap();
+        let section = xml_bank.section("progress").unwrap();
+
+        let level = section.key("level").unwrap();
+        assert_eq!(level.get(ValueType::Int), Some("12"));
+        assert_eq!(level.get(ValueType::String), None);
+        assert_eq!(
+            section.key("spawn").unwrap().get(ValueType::Point),
+            Some("12.5,40")
+        );
+        assert_eq!(
+            xml_bank.values(),
+            [
+                ("progress/level", "int:12"),
+                ("progress/speed", "fixed:1.5"),
+                ("progress/done", "flag:1"),
+                ("progress/greeting", "text:Hello there"),
+                ("progress/spawn", "point:12.5,40"),
+                ("progress/name", "runner"),
+            ]
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+        );
+
+        // A new signature keeps the bank from being written back as the text it was read from, so
+        // this checks how each type is written
+        let mut edited = xml_bank.clone();
+        edited.set_signature("1".repeat(40));
+        let written = edited.to_correctly_formatted_bank().unwrap();
+        assert_eq!(
+            written,
+            MIXED_TYPE_BANK.replace(&"0".repeat(40), &"1".repeat(40))
+        );
+
+        // Each value is signed as "Value", its type and the value itself
+        let author = "2-S2-1-1000".parse().unwrap();
+        let player = "2-S2-1-42".parse().unwrap();
+        let mut hasher = Sha1::new();
+        hasher.update("2-S2-1-10002-S2-1-42Progressprogress");
+        for key in [
+            "doneValueflag1",
+            "greetingValuetextHello there",
+            "levelValueint12",
+            "nameValuestringrunner",
+            "spawnValuepoint12.5,40",
+            "speedValuefixed1.5",
+        ] {
+            hasher.update(key);
+        }
+        assert_eq!(
+            sign("Progress", &author, &player, &xml_bank),
+            base16ct::upper::encode_string(&hasher.finalize())
+        );
+    }
+
+    #[test]
+    fn unknown_attributes_are_kept() {
+        let raw_bank = MIXED_TYPE_BANK
+            .replace(r#"<Value int="12"/>"#, r#"<Value int="12" future="x"/>"#)
+            .replace(r#"<Value flag="1"/>"#, r#"<Value future="y"/>"#);
+        let xml_bank = raw_bank.parse::<XmlBank>().unwrap();
+        let section = xml_bank.section("progress").unwrap();
+        let level = section.key("level").unwrap();
+        assert_eq!(level.get(ValueType::Int), Some("12"));
+        assert_eq!(
+            level.attributes().collect::<Vec<_>>(),
+            [("int", "12"), ("future", "x")]
+        );
+        assert_eq!(section.key("done").unwrap().to_string(), "future:y");
+
+        let mut edited = xml_bank.clone();
+        edited.set_signature("1".repeat(40));
+        assert_eq!(
+            edited.to_correctly_formatted_bank().unwrap(),
+            raw_bank.replace(&"0".repeat(40), &"1".repeat(40))
+        );
+
+        // They are signed like any other type
+        let author = "2-S2-1-1000".parse().unwrap();
+        let player = "2-S2-1-42".parse().unwrap();
+        let plain = MIXED_TYPE_BANK.parse::<XmlBank>().unwrap();
+        assert_ne!(
+            sign("Progress", &author, &player, &xml_bank),
+            sign("Progress", &author, &player, &plain)
+        );
+
+        // Anything but attributes cannot be written back, so it is not accepted
+        let with_text =
+            MIXED_TYPE_BANK.replace(r#"<Value int="12"/>"#, r#"<Value int="12">x</Value>"#);
+        let error = with_text.parse::<XmlBank>().unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("a value can only have attributes"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn typed_keys_in_runling_bank() {
+        let raw_bank = read_to_string("./data/RunlingRun004.SC2Bank").unwrap();
+        let with_int = raw_bank.replace(
+            r#"<Key name="camera">"#,
+            r#"<Key name="best"><Value int="7"/></Key><Key name="camera">"#,
+        );
+        let bank =
+            Bank::decode(with_int.parse::<XmlBank>().unwrap(), BankRegion::default()).unwrap();
+        let resaved = XmlBank::try_from(bank).unwrap();
+        let best = resaved.section("account").unwrap().key("best").unwrap();
+        assert_eq!(best.get(ValueType::Int), Some("7"));
+        let checks = resaved.verify(&BankRegion::default(), None).unwrap();
+        assert!(checks.iter().all(|check| check.passed), "{checks:#?}");
+
+        let int_account = raw_bank.replace(
+            r#"<Value string="$ jF*y^IFgl4!dUfE0!+@kN~DKQYO:(puQpNP*b`B9S!2L8}gdTt.. |R(0e:K5$nZ0A"/>"#,
+            r#"<Value int="5"/>"#,
+        );
+        let error = Bank::decode(
+            int_account.parse::<XmlBank>().unwrap(),
+            BankRegion::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(error, Error::InKey { .. }));
+        assert_eq!(
+            error.to_string(),
+            "`account/info`: expected a string value, but found `int:5`"
+        );
+    }
+
+    /// Every `.SC2Bank` file in `data`, along with variations of it in formatting that SC2 or a
+    /// text editor might produce
+    fn bank_corpus() -> Vec<(String, String)> {
+        let mut corpus = Vec::new();
+        for entry in fs::read_dir("./data").unwrap() {
+            let path = entry.unwrap().path();
+            if path
+                .extension()
+                .is_none_or(|extension| extension != "SC2Bank")
+            {
+                continue;
             }
+            let raw_bank = read_to_string(&path).unwrap();
+            let name = path.display().to_string();
+            corpus.push((
+                format!("{name} with CRLF line endings"),
+                raw_bank.replace('\n', "\r\n"),
+            ));
+            corpus.push((
+                format!("{name} without a trailing newline"),
+                raw_bank.trim_end().to_string(),
+            ));
+            corpus.push((
+                format!("{name} with spaced out attributes"),
+                raw_bank
+                    .replace("<Value string=", "<Value  string = ")
+                    .replace("\"/>", "\" />"),
+            ));
+            corpus.push((
+                format!("{name} with a different declaration"),
+                raw_bank.replace(r#"encoding="utf-8""#, r#"encoding='UTF-8'"#),
+            ));
+            corpus.push((name, raw_bank));
         }
+        assert!(corpus.len() >= 5);
+        corpus
+    }
 
-        let player_handle = derive_player_handle(checksum, &units);
+    #[test]
+    fn unmodified_banks_round_trip_exactly() {
+        for (name, raw_bank) in bank_corpus() {
+            let xml_bank = raw_bank.parse::<XmlBank>().unwrap();
+            assert_eq!(
+                xml_bank.to_correctly_formatted_bank().unwrap(),
+                raw_bank,
+                "{name}"
+            );
 
-        Self {
-            units,
-            account,
-            player_handle,
+            let resaved =
+                XmlBank::try_from(Bank::decode(xml_bank, BankRegion::default()).unwrap()).unwrap();
+            assert_eq!(
+                resaved.to_correctly_formatted_bank().unwrap(),
+                raw_bank,
+                "{name} decoded and encoded again"
+            );
         }
     }
+
+    #[test]
+    fn edited_bank_only_changes_edited_lines() {
+        let raw_bank = read_to_string("./data/RunlingRun004.SC2Bank").unwrap();
+        let mut bank =
+            Bank::decode(raw_bank.parse::<XmlBank>().unwrap(), BankRegion::default()).unwrap();
+        bank.account.total_deaths += 1;
+        let edited = XmlBank::try_from(bank)
+            .unwrap()
+            .to_correctly_formatted_bank()
+            .unwrap();
+
+        assert!(edited.ends_with("</Bank>\n"));
+        let changed_lines = raw_bank
+            .lines()
+            .zip(edited.lines())
+            .filter(|(before, after)| before != after)
+            .count();
+        assert_eq!(raw_bank.lines().count(), edited.lines().count());
+        // The camera and account info keys, and the signature
+        assert_eq!(changed_lines, 3);
+
+        let mut crlf_bank = Bank::decode(
+            raw_bank.replace('\n', "\r\n").parse::<XmlBank>().unwrap(),
+            BankRegion::default(),
+        )
+        .unwrap();
+        crlf_bank.account.total_deaths += 1;
+        let crlf_edited = XmlBank::try_from(crlf_bank)
+            .unwrap()
+            .to_correctly_formatted_bank()
+            .unwrap();
+        assert_eq!(crlf_edited, edited.replace('\n', "\r\n"));
+    }
 }
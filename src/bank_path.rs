@@ -0,0 +1,193 @@
+use std::path::Path;
+
+use crate::{
+    bank::{sign, Bank, XmlBank, BANK_NAME},
+    error::{Error, Result},
+    region::{BankRegion, Handle},
+};
+
+/// Who a bank belongs to, read from where SC2 stores it:
+/// `.../Accounts/<ACCOUNT_ID>/<PLAYER_HANDLE>/Banks/<AUTHOR_HANDLE>/<BANK_NAME>.SC2Bank`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BankPath {
+    pub account_id: String,
+    pub player: Handle,
+    pub author: Handle,
+    pub bank_name: String,
+}
+impl BankPath {
+    /// Returns `None` if the path is not laid out the way SC2 stores banks, e.g. for a backup
+    pub fn parse(path: &Path) -> Option<Self> {
+        let bank_name = path.file_stem()?.to_str()?.to_string();
+        let author_dir = path.parent()?;
+        let banks_dir = author_dir.parent()?;
+        let player_dir = banks_dir.parent()?;
+        let account_dir = player_dir.parent()?;
+
+        if banks_dir.file_name()? != "Banks" {
+            return None;
+        }
+
+        Some(Self {
+            account_id: account_dir.file_name()?.to_str()?.to_string(),
+            player: player_dir.file_name()?.to_str()?.parse().ok()?,
+            author: author_dir.file_name()?.to_str()?.parse().ok()?,
+            bank_name,
+        })
+    }
+
+    pub fn region(&self) -> BankRegion {
+        BankRegion {
+            author: self.author,
+            player_realm: self.player.realm,
+        }
+    }
+}
+
+/// What a bank is signed with: the name of the bank, the handle of the map's author and the
+/// handle of the player it belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BankIdentity {
+    pub bank_name: String,
+    pub author: Handle,
+    pub player: Handle,
+}
+impl BankIdentity {
+    /// Works out what the bank at `path` is signed with. Whatever is not given is read from the
+    /// folder the bank is in, and for Runling Run's bank can otherwise be worked out from `region`
+    /// and the bank itself. The bank's name falls back to the name of the file.
+    pub fn resolve(
+        path: &Path,
+        xml_bank: &XmlBank,
+        region: &BankRegion,
+        bank_name: Option<&str>,
+        author: Option<Handle>,
+        player: Option<Handle>,
+    ) -> Result<Self> {
+        let bank_path = BankPath::parse(path);
+        let bank_name = bank_name
+            .map(str::to_string)
+            .or_else(|| {
+                bank_path
+                    .as_ref()
+                    .map(|bank_path| bank_path.bank_name.clone())
+            })
+            .or_else(|| Some(path.file_stem()?.to_string_lossy().into_owned()))
+            .ok_or(Error::UnknownBankName)?;
+        let is_runling_run = bank_name == BANK_NAME;
+
+        let author = match (author, &bank_path) {
+            (Some(author), _) => author,
+            (None, Some(bank_path)) => bank_path.author,
+            (None, None) if is_runling_run => region.author,
+            (None, None) => return Err(Error::UnknownAuthor),
+        };
+        let player = match (player, &bank_path) {
+            (Some(player), _) => player,
+            (None, Some(bank_path)) => bank_path.player,
+            (None, None) if is_runling_run => {
+                region.player_handle(Bank::decode(xml_bank.clone(), *region)?.player_handle)
+            }
+            (None, None) => return Err(Error::UnknownPlayer),
+        };
+        Ok(Self {
+            bank_name,
+            author,
+            player,
+        })
+    }
+
+    /// The signature of `xml_bank`, see [`sign`]
+    pub fn sign(&self, xml_bank: &XmlBank) -> String {
+        sign(&self.bank_name, &self.author, &self.player, xml_bank)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs::read_to_string, path::Path};
+
+    use crate::{
+        bank::XmlBank,
+        bank_path::{BankIdentity, BankPath},
+        error::Error,
+        region::{BankRegion, Handle, Region},
+    };
+
+    #[test]
+    fn parse_bank_path() {
+        let path = Path::new(
+            "Documents/StarCraft II/Accounts/12345678/2-S2-2-111/Banks/2-S2-1-222/RunlingRun004.SC2Bank",
+        );
+        let bank_path = BankPath::parse(path).unwrap();
+
+        assert_eq!(bank_path.account_id, "12345678");
+        assert_eq!(
+            bank_path.player,
+            Handle {
+                region: Region::Europe,
+                realm: 2,
+                id: 111
+            }
+        );
+        assert_eq!(
+            bank_path.author,
+            Handle {
+                region: Region::Europe,
+                realm: 1,
+                id: 222
+            }
+        );
+        assert_eq!(bank_path.bank_name, "RunlingRun004");
+        assert_eq!(bank_path.region().player_handle(111), bank_path.player);
+
+        assert_eq!(BankPath::parse(Path::new("RunlingRun004.SC2Bank")), None);
+        assert_eq!(
+            BankPath::parse(Path::new(
+                "12345678/1-S2-1-111/Backups/1-S2-1-222/RunlingRun004.SC2Bank"
+            )),
+            None
+        );
+    }
+
+    #[test]
+    fn resolves_bank_identity() {
+        let xml_bank = read_to_string("./data/RunlingRun004.SC2Bank")
+            .unwrap()
+            .parse::<XmlBank>()
+            .unwrap();
+        let region = BankRegion::default();
+        let bank_path = Path::new("12345678/2-S2-2-111/Banks/2-S2-1-222/Other.SC2Bank");
+
+        let identity =
+            BankIdentity::resolve(bank_path, &xml_bank, &region, None, None, None).unwrap();
+        assert_eq!(identity.bank_name, "Other");
+        assert_eq!(identity.author.to_string(), "2-S2-1-222");
+        assert_eq!(identity.player.to_string(), "2-S2-2-111");
+
+        // Outside of SC2's folders, Runling Run's bank is signed for the player it belongs to
+        let backup = Path::new("backups/RunlingRun004.SC2Bank");
+        let identity = BankIdentity::resolve(backup, &xml_bank, &region, None, None, None).unwrap();
+        assert_eq!(identity.author, region.author);
+        assert_eq!(identity.player.to_string(), "1-S2-1-6930628");
+        assert_eq!(identity.sign(&xml_bank), xml_bank.signature());
+
+        // Other maps' banks need whatever is not in the path to be given
+        let other = Path::new("backups/Other.SC2Bank");
+        assert!(matches!(
+            BankIdentity::resolve(other, &xml_bank, &region, None, None, None),
+            Err(Error::UnknownAuthor)
+        ));
+        let author = Some(region.author);
+        assert!(matches!(
+            BankIdentity::resolve(other, &xml_bank, &region, None, author, None),
+            Err(Error::UnknownPlayer)
+        ));
+        let player = "1-S2-1-5".parse().ok();
+        let identity =
+            BankIdentity::resolve(other, &xml_bank, &region, Some("Named"), author, player)
+                .unwrap();
+        assert_eq!(identity.bank_name, "Named");
+        assert_eq!(identity.player, player.unwrap());
+    }
+}
@@ -0,0 +1,340 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{bank_path::BankPath, region::Handle};
+
+pub const BANK_FILE_NAME: &str = "RunlingRun004.SC2Bank";
+
+/// A Runling Run bank file found by [`find_banks`]
+#[derive(Debug, Clone)]
+pub struct FoundBank {
+    pub path: PathBuf,
+    pub bank_path: BankPath,
+    pub modified: Option<SystemTime>,
+    /// The Wine prefix the bank is in, if SC2 runs under Wine (including Lutris and Proton)
+    pub wine_prefix: Option<PathBuf>,
+}
+impl FoundBank {
+    /// Whether the bank belongs to the given account and player, where they are given
+    pub fn matches(&self, account: Option<&str>, player: Option<Handle>) -> bool {
+        account.is_none_or(|account| account == self.bank_path.account_id)
+            && player.is_none_or(|player| player == self.bank_path.player)
+    }
+}
+
+/// Lists every Runling Run bank in the current user's `Accounts` folder and in every Wine prefix.
+/// Fails if there is neither, or if the `Accounts` folder cannot be read.
+pub fn find_installed_banks() -> io::Result<Vec<FoundBank>> {
+    find_banks_in(default_accounts_root(), &wine_prefixes())
+}
+
+fn find_banks_in(
+    accounts_root: Option<PathBuf>,
+    prefixes: &[PathBuf],
+) -> io::Result<Vec<FoundBank>> {
+    if accounts_root.is_none() && prefixes.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "there is no SC2 Accounts folder or Wine prefix",
+        ));
+    }
+    let mut banks = match accounts_root {
+        Some(accounts_root) => find_banks(&accounts_root)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", accounts_root.display())))?,
+        None => Vec::new(),
+    };
+    for prefix in prefixes {
+        banks.extend(find_banks_in_prefix(prefix));
+    }
+    Ok(banks)
+}
+
+/// Picks the bank to work on out of `banks`, which has to be the only one that belongs to the
+/// given account and player. Otherwise the banks that match are returned, which are none or more
+/// than one.
+pub fn select_bank(
+    banks: Vec<FoundBank>,
+    account: Option<&str>,
+    player: Option<Handle>,
+) -> Result<FoundBank, Vec<FoundBank>> {
+    let banks = banks
+        .into_iter()
+        .filter(|bank| bank.matches(account, player))
+        .collect::<Vec<_>>();
+    <[FoundBank; 1]>::try_from(banks).map(|[bank]| bank)
+}
+
+/// Where SC2 keeps its `Accounts` folder for the current user, if there is one
+pub fn default_accounts_root() -> Option<PathBuf> {
+    let home = env::var_os("USERPROFILE").or_else(|| env::var_os("HOME"))?;
+    let root = PathBuf::from(home)
+        .join("Documents")
+        .join("StarCraft II")
+        .join("Accounts");
+    root.is_dir().then_some(root)
+}
+
+/// The Wine prefixes SC2 might be installed in: `WINEPREFIX`, the default `~/.wine`, Lutris'
+/// `~/Games/*` and Steam's Proton prefixes. Only folders that contain a `drive_c` are returned.
+pub fn wine_prefixes() -> Vec<PathBuf> {
+    let Some(home) = env::var_os("HOME") else {
+        return Vec::new();
+    };
+    wine_prefixes_in(
+        Path::new(&home),
+        env::var_os("WINEPREFIX").map(PathBuf::from),
+    )
+}
+
+fn wine_prefixes_in(home: &Path, wineprefix: Option<PathBuf>) -> Vec<PathBuf> {
+    let mut candidates = Vec::from_iter(wineprefix);
+    candidates.push(home.join(".wine"));
+    candidates.extend(subdirectories(&home.join("Games")));
+    candidates.extend(subdirectories(&home.join(".local/share/wineprefixes")));
+    for steam in [".steam/steam", ".local/share/Steam"] {
+        let compatdata = home.join(steam).join("steamapps/compatdata");
+        candidates.extend(
+            subdirectories(&compatdata)
+                .into_iter()
+                .map(|app| app.join("pfx")),
+        );
+    }
+
+    // ~/.steam/steam is usually a link to ~/.local/share/Steam, so the same prefix can turn up
+    // under more than one name
+    let mut prefixes: Vec<PathBuf> = Vec::new();
+    for candidate in candidates {
+        let Ok(prefix) = candidate.canonicalize() else {
+            continue;
+        };
+        if prefix.join("drive_c").is_dir() && !prefixes.contains(&prefix) {
+            prefixes.push(prefix);
+        }
+    }
+    prefixes
+}
+
+/// The SC2 `Accounts` folders of every Windows user in a Wine prefix, which live in
+/// `drive_c/users/<user>/Documents/StarCraft II` (`My Documents` on older Wine versions)
+pub fn accounts_roots_in_prefix(prefix: &Path) -> Vec<PathBuf> {
+    subdirectories(&prefix.join("drive_c").join("users"))
+        .into_iter()
+        .flat_map(|user| {
+            ["Documents", "My Documents"]
+                .map(|documents| user.join(documents).join("StarCraft II").join("Accounts"))
+        })
+        .filter(|root| root.is_dir())
+        .collect()
+}
+
+/// Lists every Runling Run bank in a Wine prefix, see [`accounts_roots_in_prefix`]
+pub fn find_banks_in_prefix(prefix: &Path) -> Vec<FoundBank> {
+    accounts_roots_in_prefix(prefix)
+        .iter()
+        .flat_map(|root| find_banks(root).unwrap_or_default())
+        .map(|bank| FoundBank {
+            wine_prefix: Some(prefix.to_path_buf()),
+            ..bank
+        })
+        .collect()
+}
+
+fn subdirectories(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut dirs = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect::<Vec<_>>();
+    dirs.sort();
+    dirs
+}
+
+/// Lists every Runling Run bank under an SC2 `Accounts` folder, which SC2 lays out as
+/// `<ACCOUNT_ID>/<PLAYER_HANDLE>/Banks/<AUTHOR_HANDLE>/RunlingRun004.SC2Bank`. Folders that cannot
+/// be read are skipped, so only a missing or unreadable root is an error.
+pub fn find_banks(accounts_root: &Path) -> io::Result<Vec<FoundBank>> {
+    fs::read_dir(accounts_root)?;
+
+    let mut banks = Vec::new();
+    for account_dir in subdirectories(accounts_root) {
+        for player_dir in subdirectories(&account_dir) {
+            for author_dir in subdirectories(&player_dir.join("Banks")) {
+                let path = author_dir.join(BANK_FILE_NAME);
+                if !path.is_file() {
+                    continue;
+                }
+                let Some(bank_path) = BankPath::parse(&path) else {
+                    continue;
+                };
+                let modified = fs::metadata(&path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok();
+                banks.push(FoundBank {
+                    path,
+                    bank_path,
+                    modified,
+                    wine_prefix: None,
+                });
+            }
+        }
+    }
+    Ok(banks)
+}
+
+/// Formats a time as a UTC date and time, e.g. `2024-08-30 17:05`. Only part of the library with
+/// the `cli` feature, for the command line tool's output
+#[cfg(feature = "cli")]
+pub fn format_time(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    let (days, seconds) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+    // Converts days since 1970-01-01 into a civil date, see
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs};
+
+    use crate::discover::{
+        find_banks, find_banks_in, find_banks_in_prefix, select_bank, wine_prefixes_in, FoundBank,
+        BANK_FILE_NAME,
+    };
+
+    #[test]
+    fn finds_banks_in_accounts_folder() {
+        let root = env::temp_dir().join(format!("runling-run-discover-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let bank_dirs = [
+            "111/1-S2-1-5/Banks/1-S2-1-417073",
+            "222/2-S2-1-6/Banks/2-S2-1-999",
+        ];
+        for dir in bank_dirs {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            fs::write(root.join(dir).join(BANK_FILE_NAME), "").unwrap();
+        }
+        // Other maps' banks, and stray files, are not Runling Run banks
+        fs::create_dir_all(root.join("111/1-S2-1-5/Banks/1-S2-1-1")).unwrap();
+        fs::write(root.join("111/1-S2-1-5/Banks/1-S2-1-1/Other.SC2Bank"), "").unwrap();
+        fs::write(root.join("111/1-S2-1-5/Banks/stray.txt"), "").unwrap();
+
+        let banks = find_banks(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(banks.len(), 2);
+        assert_eq!(banks[0].bank_path.account_id, "111");
+        assert_eq!(banks[0].bank_path.player.to_string(), "1-S2-1-5");
+        assert_eq!(banks[1].bank_path.author.to_string(), "2-S2-1-999");
+        assert!(banks.iter().all(|bank| bank.modified.is_some()));
+
+        // --account and --handle pick banks by the folders they are in
+        let player = "2-S2-1-6".parse().ok();
+        assert!(banks[0].matches(None, None));
+        assert!(banks[0].matches(Some("111"), None));
+        assert!(!banks[0].matches(Some("222"), None));
+        assert!(banks[1].matches(Some("222"), player));
+        assert!(!banks[0].matches(None, player));
+        let account = |bank: &FoundBank| bank.bank_path.account_id.clone();
+        let selected = select_bank(banks.clone(), None, player).unwrap();
+        assert_eq!(account(&selected), "222");
+        let matching = select_bank(banks.clone(), None, None).unwrap_err();
+        assert_eq!(matching.len(), 2);
+        assert!(select_bank(banks, Some("111"), player)
+            .unwrap_err()
+            .is_empty());
+
+        assert!(find_banks(&root).is_err());
+    }
+
+    #[test]
+    fn finds_banks_in_wine_prefixes() {
+        let home = env::temp_dir().join(format!("runling-run-wine-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&home);
+        let bank_dirs = [
+            ".wine/drive_c/users/alice/Documents/StarCraft II/Accounts/111/1-S2-1-5/Banks/1-S2-1-417073",
+            "Games/starcraft-ii/drive_c/users/bob/My Documents/StarCraft II/Accounts/222/1-S2-1-6/Banks/1-S2-1-417073",
+            ".local/share/Steam/steamapps/compatdata/123/pfx/drive_c/users/steamuser/Documents/StarCraft II/Accounts/333/1-S2-1-7/Banks/1-S2-1-417073",
+            "custom/drive_c/users/carol/Documents/StarCraft II/Accounts/444/1-S2-1-8/Banks/1-S2-1-417073",
+        ];
+        for dir in bank_dirs {
+            fs::create_dir_all(home.join(dir)).unwrap();
+            fs::write(home.join(dir).join(BANK_FILE_NAME), "").unwrap();
+        }
+        // A folder in ~/Games that is not a Wine prefix, and a second name for the Steam folder
+        fs::create_dir_all(home.join("Games/native-game")).unwrap();
+        fs::create_dir_all(home.join(".steam")).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(home.join(".local/share/Steam"), home.join(".steam/steam"))
+            .unwrap();
+
+        let prefixes = wine_prefixes_in(&home, Some(home.join("custom")));
+        let banks = prefixes
+            .iter()
+            .flat_map(|prefix| find_banks_in_prefix(prefix))
+            .collect::<Vec<_>>();
+
+        assert_eq!(prefixes.len(), 4);
+        let accounts = banks
+            .iter()
+            .map(|bank| bank.bank_path.account_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(accounts, ["444", "111", "222", "333"]);
+        assert!(banks[0].wine_prefix.as_ref().unwrap().ends_with("custom"));
+        assert!(banks[3].wine_prefix.as_ref().unwrap().ends_with("123/pfx"));
+
+        // Without an Accounts folder of its own, the banks in the prefixes are all there is
+        assert_eq!(find_banks_in(None, &prefixes).unwrap().len(), 4);
+        assert!(find_banks_in(None, &[]).is_err());
+        fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn formats_times() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        use crate::discover::format_time;
+
+        let at = |seconds| format_time(UNIX_EPOCH + Duration::from_secs(seconds));
+        assert_eq!(at(0), "1970-01-01 00:00");
+        // A leap day, and the days around the end of a year
+        assert_eq!(at(1709210096), "2024-02-29 12:34");
+        assert_eq!(at(1704067199), "2023-12-31 23:59");
+        assert_eq!(at(1704067200), "2024-01-01 00:00");
+        // 2000 is a leap year and 2100 is not, so both go on to the 1st of March
+        assert_eq!(at(951868800), "2000-03-01 00:00");
+        assert_eq!(at(4107567900), "2100-03-01 07:05");
+        // Times before 1970 cannot come from a bank file, and show as the epoch
+        assert_eq!(
+            format_time(UNIX_EPOCH - Duration::from_secs(60)),
+            "1970-01-01 00:00"
+        );
+    }
+}
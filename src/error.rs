@@ -0,0 +1,94 @@
+use num::BigUint;
+use thiserror::Error;
+
+use crate::region::Handle;
+
+/// Everything that can go wrong while reading, decoding or encoding a bank
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("invalid bank XML: {0}")]
+    Xml(#[from] quick_xml::DeError),
+    #[error("the bank has no `{0}` section")]
+    MissingSection(String),
+    #[error("the `{section}` section has no `{key}` key")]
+    MissingKey { section: String, key: String },
+    #[error("`{section}/{key}`: {source}")]
+    InKey {
+        section: String,
+        key: String,
+        source: Box<Error>,
+    },
+    #[error("expected a {expected} value, but found `{found}`")]
+    UnexpectedValueType {
+        expected: &'static str,
+        found: String,
+    },
+    #[error("invalid starcode codec: {0}")]
+    InvalidCodec(&'static str),
+    #[error("`{0}` is not a character that can appear in starcode")]
+    InvalidCharacter(char),
+    #[error("{value} is larger than the maximum of {max_val}")]
+    ValueTooLarge { value: usize, max_val: usize },
+    #[error("{field} can be at most {max_val}, but is {value}")]
+    FieldOutOfRange {
+        field: &'static str,
+        value: usize,
+        max_val: usize,
+    },
+    #[error("a sample's ciphertext is {ciphertext} characters long, but its value compresses to {plaintext}")]
+    SampleLengthMismatch { ciphertext: usize, plaintext: usize },
+    #[error("no repeating key turns the known values into the samples' ciphertexts")]
+    NoConsistentKey,
+    #[error(
+        "field {0} has neither a known maximum nor a known value, so its size cannot be found"
+    )]
+    UnconstrainedField(usize),
+    #[error("field {field} has more values than the {samples} samples")]
+    TooManyFieldValues { field: usize, samples: usize },
+    #[error("the largest radix has to be at least 2, but is {0}")]
+    RadixTooSmall(usize),
+    #[error("{remainder} is left over after reading {fields} fields, so the data holds more or larger fields than expected")]
+    LeftoverData { fields: usize, remainder: BigUint },
+    #[error("got {values} values for {fields} fields")]
+    FieldCountMismatch { values: usize, fields: usize },
+    #[error("`{0}` is not a valid positive number")]
+    InvalidNumber(String),
+    #[error("expected {expected}, got `{found}`")]
+    UnexpectedFormat {
+        expected: &'static str,
+        found: String,
+    },
+    #[error("runlings cannot have a level below 1")]
+    ZeroLevel,
+    #[error("`{0}` is not a field")]
+    UnknownField(String),
+    #[error("`{0}` is not a handle like 1-S2-1-417073")]
+    InvalidHandle(String),
+    #[error("the Runling Run 4 author's handle in {0} is not known, so it has to be given")]
+    UnknownAuthorHandle(&'static str),
+    #[error("the bank's name cannot be told from its path")]
+    UnknownBankName,
+    #[error("who published the map cannot be told from the bank's path")]
+    UnknownAuthor,
+    #[error("which player the bank belongs to cannot be told from its path")]
+    UnknownPlayer,
+    #[error("the backup failed verification: {0}")]
+    BackupFailedVerification(String),
+    #[error("the backup belongs to player {backup}, but the bank belongs to player {bank}")]
+    PlayerMismatch { backup: Handle, bank: Handle },
+    #[error("the camera key's unit checksum total of {stored} is less than the {computed} the units add up to, so the player handle cannot be derived")]
+    PlayerHandleUnderflow { stored: usize, computed: usize },
+}
+
+impl Error {
+    /// Marks the error as having happened while handling the given key
+    pub fn in_key(self, section: &str, key: &str) -> Self {
+        Error::InKey {
+            section: section.to_string(),
+            key: key.to_string(),
+            source: Box::new(self),
+        }
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,24 @@
+//! Reading, editing and re-signing of the Starcraft 2 bank files used by the arcade game
+//! 'Runling Run 4'.
+//!
+//! [`XmlBank`] is the bank file as it is stored on disk, and [`Bank`] is the decoded account and
+//! units inside it. Converting a [`Bank`] back into an [`XmlBank`] regenerates all of the
+//! checksums and the signature, so the result can be written straight back to the bank file.
+
+pub mod analysis;
+pub mod bank;
+pub mod bank_path;
+pub mod discover;
+pub mod error;
+pub mod model;
+#[cfg(feature = "cli")]
+pub mod parse;
+pub mod record;
+pub mod region;
+pub mod starcode;
+
+pub use bank::{sign, Bank, Check, Value, ValueType, XmlBank};
+pub use bank_path::{BankIdentity, BankPath};
+pub use error::{Error, Result};
+pub use model::{Account, Runling, RunlingType};
+pub use region::{BankRegion, Handle, Region};
@@ -1,16 +1,22 @@
 use std::{
+    fmt::Display,
     fs::{self, read_to_string, write},
-    io::{BufRead, Write},
+    io::{stdin, stdout, BufRead, IsTerminal, Read, Write},
     path::Path,
+    process,
 };
 
-use bank::{Bank, XmlBank};
-use clap::{Parser, Subcommand, ValueEnum};
-use model::Runling;
-
-mod bank;
-mod model;
-mod starcode;
+use clap::{Parser, Subcommand};
+use runling_run_edit::{
+    analysis::{discover_layout, recover_key, FieldHint},
+    discover::{
+        find_banks, find_installed_banks, format_time, select_bank as select_bank_matching,
+        FoundBank,
+    },
+    parse::{parse_assignment, parse_numbers, parse_optional_numbers, strip_line_break},
+    starcode::{pack, parse_payload, unpack, Codec, RUNLING_RUN_ALPHABET},
+    Bank, BankIdentity, BankPath, BankRegion, Error, Handle, Region, Runling, RunlingType, XmlBank,
+};
 
 #[derive(Debug, Parser)]
 #[command(
@@ -20,15 +26,31 @@ struct Args {
     #[arg(
         short,
         long,
-        help = "The location of the SC2 Runling Run bank file that you want to edit. This is usually located (on windows) at ~/Documents/Starcraft II/Accounts/<ACCOUNT_ID>/1-S2-1-<USER_ID>/Banks/1-S2-1-417073/RunlingRun004.SC2Bank"
+        conflicts_with_all = ["account", "handle"],
+        help = "The location of the SC2 Runling Run bank file that you want to edit. This is usually located (on windows) at ~/Documents/Starcraft II/Accounts/<ACCOUNT_ID>/1-S2-1-<USER_ID>/Banks/1-S2-1-417073/RunlingRun004.SC2Bank. If not given, the bank is found automatically (see the discover command)"
+    )]
+    file_location: Option<String>,
+    #[arg(
+        long,
+        help = "The SC2 Accounts folder to look for banks in, when --file-location is not given. Defaults to ~/Documents/StarCraft II/Accounts, plus the ones in any Wine, Lutris or Proton prefixes"
     )]
-    file_location: String,
+    accounts_root: Option<String>,
+    #[arg(
+        long,
+        help = "Picks the bank of the account with this ID (the number the account's folder is named after), when --file-location is not given"
+    )]
+    account: Option<String>,
+    #[arg(
+        long,
+        help = "Picks the bank of the player with this handle, e.g. 1-S2-1-1234567, when --file-location is not given"
+    )]
+    handle: Option<Handle>,
     #[arg(
         short,
         long,
-        help = "The location to store an unmodified backup of your bank file"
+        help = "The location to store an unmodified backup of your bank file. Required for every command that modifies the bank"
     )]
-    backup_location: String,
+    backup_location: Option<String>,
     #[arg(
         short,
         long,
@@ -36,33 +58,131 @@ struct Args {
         help = "Whether to overwrite the backup file if it already exists"
     )]
     overwrite_backup: bool,
+    #[arg(
+        short,
+        long,
+        default_value_t = false,
+        help = "Answer yes to every confirmation prompt, so that commands can be run without any user input"
+    )]
+    yes: bool,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Show the changes a command would make to the bank instead of saving them. No backup is made in this mode"
+    )]
+    dry_run: bool,
+    #[arg(
+        long,
+        help = "The Battle.net region the bank is from, which changes how it is signed. Inferred from the bank's folder name when not given, and otherwise defaults to americas. Runling Run 4's author handle is only known for americas, so for the other regions the bank has to be in its SC2 folder or --author-handle has to be given as well"
+    )]
+    region: Option<Region>,
+    #[arg(
+        long,
+        help = "The handle Runling Run 4 is published under in the bank's region, e.g. 2-S2-1-123456. This is the name of the folder the bank is in, and is only needed if that folder has been renamed or the bank was moved"
+    )]
+    author_handle: Option<Handle>,
 
     #[command(subcommand)]
     command: Command,
 }
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
-enum RunlingType {
-    Zergling,
-    Baneling,
-    Hydralisk,
-    Ultralisk,
-    Roach,
-}
-impl RunlingType {
-    pub fn to_bank_data(&self) -> usize {
-        match self {
-            RunlingType::Zergling => 1,
-            RunlingType::Baneling => 2,
-            RunlingType::Hydralisk => 3,
-            RunlingType::Ultralisk => 4,
-            RunlingType::Roach => 5,
-        }
+impl Args {
+    /// The bank file to work on, which is always known once `main` has looked for it
+    fn bank_file(&self) -> &str {
+        self.file_location
+            .as_deref()
+            .expect("The bank file is found before any command runs")
     }
 }
 
-#[derive(Debug, Subcommand)]
+#[derive(Debug, Clone, Subcommand)]
 enum Command {
+    #[command(
+        about = "Lists every Runling Run bank in the SC2 Accounts folder, with a short summary of each. Use --account or --handle to pick one of them for the other commands instead of --file-location."
+    )]
+    Discover,
+    #[command(
+        about = "Works out a map's starcode encryption key and its length from encrypted values whose contents are known, e.g. after the author changes the key in a new version. Does not use a bank file."
+    )]
+    RecoverKey {
+        #[arg(
+            long = "sample",
+            num_args = 2,
+            value_names = ["CIPHERTEXT", "VALUE"],
+            allow_hyphen_values = true,
+            required = true,
+            help = "An encrypted value and what it is known to hold: either the number it uncompresses to, or its fields as VALUE/MAX pairs separated by commas in the order they are stored, e.g. 3/1000,120/50000. Can be given more than once"
+        )]
+        samples: Vec<String>,
+        #[arg(
+            long,
+            default_value = RUNLING_RUN_ALPHABET,
+            help = "The characters the map's starcode is written with"
+        )]
+        alphabet: String,
+    },
+    #[command(
+        about = "Proposes how an unknown starcode record splits into fields, from several of its payloads and what is known about their fields. Does not use a bank file."
+    )]
+    FindLayout {
+        #[arg(
+            long = "sample",
+            num_args = 2,
+            value_names = ["PAYLOAD", "VALUES"],
+            required = true,
+            help = "A payload and the values of its fields in the order they are stored, separated by commas, with ? for fields whose value is not known, e.g. 1,?,15. Can be given more than once"
+        )]
+        samples: Vec<String>,
+        #[arg(
+            long,
+            help = "The payloads are encrypted values from a Runling Run bank instead of uncompressed numbers"
+        )]
+        encrypted: bool,
+        #[arg(
+            long,
+            help = "The fields' maximum values where they are known, in the same form as the sample values"
+        )]
+        max_vals: Option<String>,
+        #[arg(
+            long,
+            default_value_t = 1_000_000_000,
+            value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(2..),
+            help = "The largest maximum value a field is assumed to have, plus one"
+        )]
+        max_radix: usize,
+        #[arg(long, default_value_t = 10, help = "How many layouts to print at most")]
+        limit: usize,
+    },
+    #[command(
+        about = "Decrypts, encrypts, uncompresses, compresses, unpacks or packs a single starcode value with the codec Runling Run banks use, e.g. to inspect a value from a bank or build one by hand. Does not use a bank file."
+    )]
+    Starcode {
+        #[command(subcommand)]
+        command: StarcodeCommand,
+    },
+    #[command(
+        about = "Prints the decoded account and all unit slots without modifying anything. Does not require a backup location."
+    )]
+    Show,
+    #[command(
+        about = "Checks that the bank's signature and checksums match its contents, which SC2 requires before it will load the bank. Does not require a backup location."
+    )]
+    Verify,
+    #[command(
+        about = "Recomputes the bank's signature and stores it, so SC2 accepts a bank that was edited by hand. Works for the bank of any map, not just Runling Run 4."
+    )]
+    Resign {
+        #[arg(
+            long,
+            help = "The name the bank is signed with, which is its file name without .SC2Bank. Defaults to the name of the bank file"
+        )]
+        bank_name: Option<String>,
+        #[arg(
+            long,
+            help = "The handle of the player the bank belongs to, e.g. 1-S2-1-1234567. Defaults to the one in the bank's path, or for Runling Run 4 banks the one stored in the bank"
+        )]
+        player_handle: Option<Handle>,
+    },
     #[command(
         about = "Unlocks the two score-gated playable units by setting your total score to 50,000."
     )]
@@ -77,100 +197,1026 @@ enum Command {
         runling_type: RunlingType,
         #[arg(short, long)]
         level: usize,
+        #[arg(
+            short,
+            long,
+            help = "The save slot to put the new unit in, from 0 to 7. If not given, you will be asked for it"
+        )]
+        slot: Option<usize>,
+    },
+    #[command(
+        about = "Sets one or more account fields by name, e.g. `edit-account --set total_score=50000 --set hard_wins=3`. Field names are the ones listed by the `show` command."
+    )]
+    EditAccount {
+        #[arg(
+            short,
+            long = "set",
+            value_name = "FIELD=VALUE",
+            required = true,
+            value_parser = parse_assignment,
+            help = "A field to change and the value to set it to"
+        )]
+        assignments: Vec<(String, usize)>,
+    },
+    #[command(
+        about = "Changes the stats of the runling in an existing save slot. Any stat that is not given keeps its current value."
+    )]
+    EditUnit {
+        #[arg(
+            short,
+            long,
+            help = "The save slot of the runling to edit, from 0 to 7"
+        )]
+        slot: usize,
+        #[arg(long)]
+        experience: Option<usize>,
+        #[arg(long)]
+        energy_regeneration: Option<usize>,
+        #[arg(long)]
+        maximum_energy: Option<usize>,
+        #[arg(long)]
+        speed: Option<usize>,
+        #[arg(long)]
+        skill_1_level: Option<usize>,
+        #[arg(long)]
+        skill_2_level: Option<usize>,
+        #[arg(long)]
+        runling_level: Option<usize>,
+        #[arg(long)]
+        remaining_points: Option<usize>,
+    },
+    #[command(about = "Deletes the runling in the given save slot")]
+    DeleteUnit {
+        #[arg(short, long, help = "The save slot to empty, from 0 to 7")]
+        slot: usize,
+    },
+    #[command(about = "Moves a runling to another save slot, leaving its old slot empty")]
+    MoveUnit {
+        #[arg(long)]
+        from: usize,
+        #[arg(long)]
+        to: usize,
+    },
+    #[command(about = "Swaps the contents of two save slots")]
+    SwapUnits {
+        #[arg(long)]
+        first: usize,
+        #[arg(long)]
+        second: usize,
+    },
+    #[command(about = "Copies a runling into another save slot")]
+    CloneUnit {
+        #[arg(long)]
+        from: usize,
+        #[arg(long)]
+        to: usize,
+    },
+    #[command(
+        about = "Replaces the bank with a backup of it, after checking that the backup is valid and belongs to the same player. The current bank is saved to the backup location first."
+    )]
+    Restore {
+        #[arg(long, help = "The backup file to restore")]
+        from: String,
+    },
+}
+
+/// The starcode values handled by the starcode subcommands are read from stdin if not given
+#[derive(Debug, Clone, Subcommand)]
+enum StarcodeCommand {
+    #[command(about = "Decrypts an encrypted value, e.g. a bank key's value")]
+    Decrypt {
+        #[arg(allow_hyphen_values = true)]
+        value: Option<String>,
+    },
+    #[command(about = "Encrypts a compressed value, so it can be stored in a bank")]
+    Encrypt {
+        #[arg(allow_hyphen_values = true)]
+        value: Option<String>,
+    },
+    #[command(about = "Turns a decrypted value into the number it holds")]
+    Uncompress {
+        #[arg(allow_hyphen_values = true)]
+        value: Option<String>,
+    },
+    #[command(about = "Turns a number into a compressed value")]
+    Compress { number: Option<String> },
+    #[command(about = "Splits a number into its fields, printed in the order they are stored")]
+    Unpack {
+        #[arg(
+            long,
+            required = true,
+            help = "The maximum value of each field in the order they are stored, separated by commas, e.g. 300000,8100000"
+        )]
+        max_vals: String,
+        number: Option<String>,
+    },
+    #[command(about = "Stores fields in a number, the reverse of unpack")]
+    Pack {
+        #[arg(long, required = true, help = "The same as for unpack")]
+        max_vals: String,
+        #[arg(help = "The field values in the order they are stored, separated by commas")]
+        values: Option<String>,
     },
 }
 
+fn print_recovered_key(samples: &[String], alphabet: &str) {
+    let samples = samples
+        .chunks(2)
+        .map(|sample| {
+            let value = sample[1].parse().or_fail("Invalid sample value");
+            (sample[0].clone(), value)
+        })
+        .collect::<Vec<_>>();
+    let recovered = recover_key(alphabet, &samples).or_fail("Could not recover the key");
+
+    let key = recovered
+        .characters
+        .iter()
+        .map(|c| c.unwrap_or('?'))
+        .collect::<String>();
+    println!("Key length: {}", recovered.period);
+    println!("Key: {key}");
+    if recovered.key().is_none() {
+        println!("The characters shown as ? were not covered by any sample");
+    }
+    if !recovered.confirmed {
+        println!("Warning: no sample is long enough to repeat the key, so the key may be longer than this. Give more or longer samples to be sure");
+    }
+}
+
+fn print_layouts(
+    samples: &[String],
+    encrypted: bool,
+    max_vals: Option<&str>,
+    max_radix: usize,
+    limit: usize,
+) {
+    let codec = Codec::runling_run();
+    let mut payloads = Vec::new();
+    let mut sample_values = Vec::new();
+    for sample in samples.chunks(2) {
+        let payload = if encrypted {
+            codec
+                .decrypt(&sample[0])
+                .and_then(|data| codec.uncompress(&data))
+                .or_fail(&format!("Could not decode `{}`", sample[0]))
+        } else {
+            parse_payload(&sample[0]).or_fail("Invalid payload")
+        };
+        payloads.push(payload);
+        sample_values.push(parse_optional_numbers(&sample[1]).or_fail("Invalid sample values"));
+    }
+    let max_vals = max_vals
+        .map(|max_vals| parse_optional_numbers(max_vals).or_fail("Invalid --max-vals"))
+        .unwrap_or_default();
+
+    let fields = FieldHint::from_samples(&sample_values, &max_vals);
+    let layouts = discover_layout(&payloads, &fields, max_radix, limit)
+        .or_fail("Could not search for layouts");
+
+    if layouts.is_empty() {
+        println!("No layout fits every sample");
+        return;
+    }
+    for (number, layout) in layouts.iter().enumerate() {
+        println!("Layout {}:", number + 1);
+        for line in layout.to_string().lines() {
+            println!("  {line}");
+        }
+    }
+    if layouts.len() == limit {
+        println!("Stopped after {limit} layouts, there may be more. Give more samples or known values to narrow them down");
+    }
+}
+
+/// The argument if given, otherwise all of stdin without its final line break
+fn argument_or_stdin(argument: &Option<String>) -> String {
+    if let Some(argument) = argument {
+        return argument.clone();
+    }
+    let mut input = String::new();
+    stdin()
+        .read_to_string(&mut input)
+        .or_fail("Failed to read stdin");
+    strip_line_break(&input).to_string()
+}
+
+fn run_starcode_command(command: &StarcodeCommand) {
+    let codec = Codec::runling_run();
+    let parse_number = |number: &Option<String>| {
+        parse_payload(&argument_or_stdin(number)).or_fail("Invalid number")
+    };
+    let parse_max_vals = |max_vals: &str| parse_numbers(max_vals).or_fail("Invalid --max-vals");
+    match command {
+        StarcodeCommand::Decrypt { value } => {
+            let decrypted = codec.decrypt(&argument_or_stdin(value));
+            println!("{}", decrypted.or_fail("Could not decrypt the value"));
+        }
+        StarcodeCommand::Encrypt { value } => {
+            let encrypted = codec.encrypt(&argument_or_stdin(value));
+            println!("{}", encrypted.or_fail("Could not encrypt the value"));
+        }
+        StarcodeCommand::Uncompress { value } => {
+            let number = codec.uncompress(&argument_or_stdin(value));
+            println!("{}", number.or_fail("Could not uncompress the value"));
+        }
+        StarcodeCommand::Compress { number } => {
+            println!("{}", codec.compress(&parse_number(number)))
+        }
+        StarcodeCommand::Unpack { max_vals, number } => {
+            let values = unpack(parse_number(number), &parse_max_vals(max_vals))
+                .or_fail("Could not unpack the number");
+            let values = values.iter().map(ToString::to_string).collect::<Vec<_>>();
+            println!("{}", values.join(","));
+        }
+        StarcodeCommand::Pack { max_vals, values } => {
+            let values = parse_numbers(&argument_or_stdin(values)).or_fail("Invalid values");
+            let data =
+                pack(&values, &parse_max_vals(max_vals)).or_fail("Could not pack the values");
+            println!("{data}");
+        }
+    }
+}
+
+/// Prints `message` as an error and exits, for problems with the user's input rather than bugs
+fn fail(message: &str) -> ! {
+    eprintln!("Error: {message}");
+    process::exit(1)
+}
+
+/// Turns errors into a readable message and exit, instead of a panic
+trait OrFail<T> {
+    fn or_fail(self, context: &str) -> T;
+}
+impl<T, E: Display> OrFail<T> for Result<T, E> {
+    fn or_fail(self, context: &str) -> T {
+        self.unwrap_or_else(|e| fail(&format!("{context}: {e}")))
+    }
+}
+
 fn get_user_input(prompt: &str) -> String {
+    if !stdin().is_terminal() {
+        fail(&format!(
+            "Needed an answer to \"{}\", but stdin is not a terminal. Pass the answer as a flag instead (e.g. --yes or --slot), see --help for details.",
+            prompt.trim()
+        ));
+    }
     print!("{prompt}");
-    std::io::stdout().flush().unwrap();
-    std::io::stdin().lock().lines().next().unwrap().unwrap()
+    stdout().flush().unwrap();
+    match stdin().lock().lines().next() {
+        Some(Ok(line)) => line,
+        _ => fail("No answer was given"),
+    }
 }
 
-fn save_bank(bank: Bank, save_location: &str) {
-    println!("Saving edited bank data");
-    let xml_bank = XmlBank::from(bank);
-    let raw_bank = xml_bank.to_correctly_formatted_bank();
-    write(save_location, raw_bank).expect("Failed to write edited bank data");
+/// Asks a y/n question, unless `yes` is set in which case the answer is always yes
+fn confirm(yes: bool, prompt: &str) -> bool {
+    yes || get_user_input(prompt) == "y"
 }
 
-fn main() {
-    let args = Args::parse();
-    let file_location = Path::new(&args.file_location);
-    let backup_location = Path::new(&args.backup_location);
-    assert!(
-        file_location.exists(),
-        "Passed bank file location does not exist"
+fn class_name(class: usize) -> String {
+    RunlingType::from_bank_data(class)
+        .map(|class| class.name().to_string())
+        .unwrap_or_else(|| format!("Unknown ({class})"))
+}
+
+fn check_slot(slot: usize) {
+    if slot > 7 {
+        fail("Must input a slot between 0 and 7, inclusive.");
+    }
+}
+
+fn describe_unit(unit: &Runling) -> String {
+    format!(
+        "a level {} {} with {} experience",
+        unit.runling_level,
+        class_name(unit.class),
+        unit.experience
+    )
+}
+
+/// Asks for confirmation before `slot` is overwritten or emptied, if there is a runling in it
+fn confirm_slot_loss(yes: bool, bank: &Bank, slot: usize, action: &str) -> bool {
+    match &bank.units[slot] {
+        Some(unit) => confirm(
+            yes,
+            &format!(
+                "Slot {slot} currently has {} in it. Are you sure you want to {action} it? y/n ",
+                describe_unit(unit)
+            ),
+        ),
+        None => true,
+    }
+}
+
+fn load_xml_bank_checked(location: &Path) -> Result<XmlBank, String> {
+    read_to_string(location)
+        .map_err(|e| e.to_string())?
+        .parse()
+        .map_err(|e: Error| e.to_string())
+}
+
+fn load_xml_bank(location: &str) -> XmlBank {
+    let raw_bank: String = read_to_string(location).or_fail(&format!("Failed to read {location}"));
+    raw_bank
+        .parse()
+        .or_fail(&format!("Failed to parse {location}"))
+}
+
+fn bank_region(args: &Args, bank_path: Option<&BankPath>) -> BankRegion {
+    BankRegion::resolve(args.author_handle, args.region, bank_path)
+        .or_fail("Cannot sign the bank, pass its folder name as --author-handle")
+}
+
+fn discover_banks(args: &Args) -> Vec<FoundBank> {
+    match &args.accounts_root {
+        Some(accounts_root) => find_banks(Path::new(accounts_root))
+            .or_fail(&format!("Failed to search {accounts_root} for banks")),
+        None => find_installed_banks()
+            .or_fail("Failed to search for banks, pass the SC2 Accounts folder as --accounts-root"),
+    }
+}
+
+/// Picks the bank to work on when no --file-location was given
+fn select_bank(args: &Args) -> FoundBank {
+    select_bank_matching(discover_banks(args), args.account.as_deref(), args.handle)
+        .unwrap_or_else(|banks| {
+            if banks.is_empty() {
+                fail("No Runling Run bank was found that matches --account and --handle, use the discover command to list them")
+            }
+            print_found_banks(&banks);
+            fail("More than one bank was found, pick one with --account or --handle, or pass --file-location")
+        })
+}
+
+fn print_found_banks(banks: &[FoundBank]) {
+    if banks.is_empty() {
+        println!("No Runling Run banks were found");
+        return;
+    }
+    println!(
+        "{:<12}{:<18}{:<14}{:<18}Summary",
+        "Account", "Handle", "Region", "Modified (UTC)"
     );
-    if !args.overwrite_backup {
-        assert!(
-            !backup_location.exists(),
-            "There already exists a file at the backup location - if you want to overwrite it, use the --overwrite-backup flag"
+    for found in banks {
+        let summary = load_xml_bank_checked(&found.path)
+            .and_then(|xml_bank| {
+                Bank::decode(xml_bank, found.bank_path.region()).map_err(|e| e.to_string())
+            })
+            .map(|bank| {
+                let account = &bank.account;
+                format!(
+                    "score {}, {} games, {} units",
+                    account.total_score,
+                    account.normal_games
+                        + account.hard_games
+                        + account.insane_games
+                        + account.time_games,
+                    bank.units.iter().flatten().count()
+                )
+            })
+            .unwrap_or_else(|e| format!("unreadable: {e}"));
+        println!(
+            "{:<12}{:<18}{:<14}{:<18}{}",
+            found.bank_path.account_id,
+            found.bank_path.player.to_string(),
+            found.bank_path.player.region.name(),
+            found.modified.map(format_time).unwrap_or_default(),
+            summary
         );
+        println!("    {}", found.path.display());
+        if let Some(prefix) = &found.wine_prefix {
+            println!("    in Wine prefix {}", prefix.display());
+        }
+    }
+}
+
+/// Decodes the bank being edited. When it is stored where SC2 keeps it, the player handle in the
+/// path is checked against the one derived from the bank, and used if the bank's is unreadable.
+fn load_bank(xml_bank: XmlBank, region: BankRegion, bank_path: Option<&BankPath>) -> Bank {
+    let Some(bank_path) = bank_path else {
+        return decode_bank(xml_bank, region);
+    };
+
+    let (bank, camera_error) = Bank::decode_with_fallback(xml_bank, region, bank_path.player.id)
+        .or_fail("Failed to decode bank");
+    if let Some(camera_error) = camera_error {
+        eprintln!(
+            "Warning: the player handle could not be derived from the bank ({camera_error}), so {} from the bank's path is used instead",
+            bank_path.player
+        );
+    } else if bank.region.player_handle(bank.player_handle) != bank_path.player {
+        eprintln!(
+            "Warning: the bank belongs to player {}, but is stored in the folder of player {}. SC2 will not accept it there",
+            bank.region.player_handle(bank.player_handle),
+            bank_path.player
+        );
+    }
+    bank
+}
+
+fn decode_bank(xml_bank: XmlBank, region: BankRegion) -> Bank {
+    Bank::decode(xml_bank, region).or_fail("Failed to decode bank")
+}
+
+fn save_bank(args: &Args, original: &XmlBank, bank: Bank) {
+    let (region, player_handle) = (bank.region, bank.player_handle);
+    let xml_bank = XmlBank::try_from(bank).or_fail("Failed to encode the edited bank");
+    if args.dry_run {
+        print_diff(original, &xml_bank, region, player_handle);
+        println!("Dry run, so the bank file was not changed");
+        return;
+    }
+    write_bank(args, &xml_bank);
+}
+
+/// Signs the bank again without decoding it, so that it works for any map's bank
+fn resign(
+    args: &Args,
+    OpenBank {
+        mut xml_bank,
+        region,
+        ..
+    }: OpenBank,
+    bank_name: Option<&str>,
+    player_handle: Option<Handle>,
+) {
+    let identity = BankIdentity::resolve(
+        Path::new(args.bank_file()),
+        &xml_bank,
+        &region,
+        bank_name,
+        args.author_handle,
+        player_handle,
+    )
+    .unwrap_or_else(|e| {
+        let flag = match e {
+            Error::UnknownBankName => "--bank-name",
+            Error::UnknownAuthor => "the name of the folder the bank is in as --author-handle",
+            Error::UnknownPlayer => "--player-handle",
+            _ => fail(&format!("Failed to decode bank: {e}")),
+        };
+        fail(&format!("Cannot sign the bank, {e}. Pass {flag}"))
+    });
+
+    let signature = identity.sign(&xml_bank);
+    println!(
+        "Signing {} by {} for player {}",
+        identity.bank_name, identity.author, identity.player
+    );
+    if signature == xml_bank.signature() {
+        println!("The signature is already correct, so the bank file was not changed");
+        return;
     }
+    println!("    Signature: {} -> {signature}", xml_bank.signature());
+    if args.dry_run {
+        println!("Dry run, so the bank file was not changed");
+        return;
+    }
+    xml_bank.set_signature(signature);
+    write_bank(args, &xml_bank);
+}
+
+/// Backs up the bank and then overwrites it
+fn write_bank(args: &Args, xml_bank: &XmlBank) {
+    create_backup(args);
+    println!("Saving edited bank data");
+    let raw_bank = xml_bank
+        .to_correctly_formatted_bank()
+        .or_fail("Failed to encode the edited bank");
+    write(args.bank_file(), raw_bank).or_fail("Failed to write edited bank data");
+}
+
+fn same_file(a: &str, b: &str) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn check_backup_location(args: &Args) -> &str {
+    let backup_location = args.backup_location.as_deref().unwrap_or_else(|| {
+        fail("This command modifies the bank, so a --backup-location must be given")
+    });
+    if !args.overwrite_backup && Path::new(backup_location).exists() {
+        fail("There already exists a file at the backup location - if you want to overwrite it, use the --overwrite-backup flag");
+    }
+    backup_location
+}
 
-    fs::copy(&args.file_location, &args.backup_location)
-        .expect("Failed to copy original bank to backup file location");
+fn create_backup(args: &Args) {
+    let backup_location = check_backup_location(args);
+    fs::copy(args.bank_file(), backup_location)
+        .or_fail("Failed to copy original bank to backup file location");
     println!("Successfully created backup file");
+}
+
+fn print_field_changes(
+    before: impl IntoIterator<Item = (&'static str, usize)>,
+    after: impl IntoIterator<Item = (&'static str, usize)>,
+) {
+    for ((name, before), (_, after)) in before.into_iter().zip(after) {
+        if before != after {
+            println!("    {name:<24} {before:>10} -> {after}");
+        }
+    }
+}
+
+/// Prints the decoded fields and the raw key values that differ between two versions of a bank.
+/// `player_handle` is the one [`load_bank`] settled on, and is used for a bank whose camera key
+/// cannot be decoded.
+fn print_diff(original: &XmlBank, edited: &XmlBank, region: BankRegion, player_handle: usize) {
+    let decode = |xml_bank: &XmlBank| {
+        Bank::decode_with_fallback(xml_bank.clone(), region, player_handle).map(|(bank, _)| bank)
+    };
+    match (decode(original), decode(edited)) {
+        (Ok(before), Ok(after)) => print_decoded_diff(&before, &after),
+        (Err(e), _) | (_, Err(e)) => {
+            println!(
+                "Account and unit changes cannot be shown, since a bank cannot be decoded: {e}"
+            )
+        }
+    }
+
+    println!("Raw bank changes:");
+    let quoted = |value: Option<String>| {
+        value
+            .map(|value| format!("\"{value}\""))
+            .unwrap_or_else(|| "(missing)".to_string())
+    };
+    for (key, before, after) in original.changed_values(edited) {
+        println!("    {key}: {} -> {}", quoted(before), quoted(after));
+    }
+    if original.signature() != edited.signature() {
+        println!(
+            "    Signature: {} -> {}",
+            original.signature(),
+            edited.signature()
+        );
+    }
+}
+
+fn print_decoded_diff(before: &Bank, after: &Bank) {
+    println!("Account changes:");
+    if before.account == after.account {
+        println!("    (none)");
+    }
+    print_field_changes(before.account.fields(), after.account.fields());
+
+    println!("Unit changes:");
+    if before.units == after.units {
+        println!("    (none)");
+    }
+    for slot in 0..8 {
+        match (&before.units[slot], &after.units[slot]) {
+            (Some(before), Some(after)) if before != after => {
+                println!("  Slot {slot}:");
+                print_field_changes(before.fields(), after.fields());
+            }
+            (Some(before), None) => {
+                println!("  Slot {slot}: {} -> (empty)", describe_unit(before))
+            }
+            (None, Some(after)) => {
+                println!("  Slot {slot}: (empty) -> {}", describe_unit(after))
+            }
+            _ => {}
+        }
+    }
+}
 
-    let raw_bank: String = read_to_string(&args.file_location).unwrap();
-    let xml_bank: XmlBank = quick_xml::de::from_str(&raw_bank).unwrap();
-    let mut bank = Bank::from(xml_bank);
+fn print_bank(bank: &Bank) {
+    println!(
+        "Player handle: {} ({})",
+        bank.region.player_handle(bank.player_handle),
+        bank.region.region().name()
+    );
+    println!();
+    println!("Account");
+    for (name, value) in bank.account.fields() {
+        println!("    {name:<24} {value:>10}");
+    }
+    println!();
+    println!(
+        "{:<6}{:<11}{:>7}{:>12}{:>14}{:>12}{:>7}{:>9}{:>9}{:>12}",
+        "Slot",
+        "Class",
+        "Level",
+        "Experience",
+        "Energy regen",
+        "Max energy",
+        "Speed",
+        "Skill 1",
+        "Skill 2",
+        "Unspent"
+    );
+    for (slot, unit) in bank.units.iter().enumerate() {
+        match unit {
+            Some(unit) => {
+                println!(
+                    "{:<6}{:<11}{:>7}{:>12}{:>14}{:>12}{:>7}{:>9}{:>9}{:>12}",
+                    slot,
+                    class_name(unit.class),
+                    unit.runling_level,
+                    unit.experience,
+                    unit.energy_regeneration,
+                    unit.maximum_energy,
+                    unit.speed,
+                    unit.skill_1_level,
+                    unit.skill_2_level,
+                    unit.remaining_points
+                );
+            }
+            None => println!("{slot:<6}(empty)"),
+        }
+    }
+}
+
+/// A bank file read for one of the commands that work on one
+struct OpenBank {
+    xml_bank: XmlBank,
+    bank_path: Option<BankPath>,
+    region: BankRegion,
+}
 
+/// Finds the bank file to work on, unless --file-location was given, and reads it
+fn open_bank(args: &mut Args) -> OpenBank {
+    if args.file_location.is_none() {
+        let bank = select_bank(args);
+        println!("Using bank file {}", bank.path.display());
+        args.file_location = Some(bank.path.to_string_lossy().into_owned());
+    }
+    if !Path::new(args.bank_file()).exists() {
+        fail("Passed bank file location does not exist");
+    }
+
+    let xml_bank = load_xml_bank(args.bank_file());
+    let bank_path = BankPath::parse(Path::new(args.bank_file()));
+    let region = bank_region(args, bank_path.as_ref());
     println!("Successfully read and parsed bank file");
+    OpenBank {
+        xml_bank,
+        bank_path,
+        region,
+    }
+}
 
-    match args.command {
+/// A decoded bank for one of the commands that edit it, along with the bank as it was read
+struct Edit {
+    original: XmlBank,
+    bank: Bank,
+}
+
+/// Opens and decodes the bank, and checks that the backup can be made before anything is changed
+fn open_for_edit(args: &mut Args) -> Edit {
+    let OpenBank {
+        xml_bank,
+        bank_path,
+        region,
+    } = open_bank(args);
+    let original = xml_bank.clone();
+    let bank = load_bank(xml_bank, region, bank_path.as_ref());
+    if !args.dry_run {
+        check_backup_location(args);
+    }
+    Edit { original, bank }
+}
+
+fn show(opened: OpenBank) {
+    let bank = load_bank(opened.xml_bank, opened.region, opened.bank_path.as_ref());
+    print_bank(&bank);
+}
+
+fn verify(opened: OpenBank) {
+    let player_handle = opened
+        .bank_path
+        .as_ref()
+        .map(|bank_path| bank_path.player.id);
+    let checks = opened
+        .xml_bank
+        .verify(&opened.region, player_handle)
+        .or_fail("Failed to decode bank");
+    for check in &checks {
+        let result = if check.passed { "PASS" } else { "FAIL" };
+        println!("[{result}] {} ({})", check.description, check.details);
+    }
+    if checks.iter().any(|check| !check.passed) {
+        fail("The bank failed verification");
+    }
+}
+
+fn unlock_all_units(args: &Args, Edit { original, mut bank }: Edit) {
+    if confirm(
+        args.yes,
+        &format!(
+            "Are you sure you want to overwrite your current score of {} to 50,000? y/n ",
+            bank.account.total_score
+        ),
+    ) {
+        bank.account.total_score = 50000;
+        save_bank(args, &original, bank)
+    } else {
+        println!("Action cancelled, exiting")
+    }
+}
+
+fn create_new_unit(
+    args: &Args,
+    Edit { original, mut bank }: Edit,
+    runling_type: RunlingType,
+    level: usize,
+    slot: Option<usize>,
+) {
+    let new_unit = Runling::new(runling_type, level)
+        .or_fail(&format!("Cannot create a level {level} runling"));
+
+    let empty_save_slots = (0..=7_usize)
+        .filter(|i| bank.units[*i].is_none())
+        .collect::<Vec<_>>();
+
+    let new_unit_slot = slot.unwrap_or_else(|| {
+        get_user_input(&format!("Which save slot would you like to overwrite with the new unit? Currently slots {empty_save_slots:?} are empty. If you choose to overwrite a full save slot, a confirmation prompt with the unit information will be shown first. \n> "))
+            .parse::<usize>()
+            .or_fail("Must input a valid positive number")
+    });
+    // Don't need to check whether it's greater than zero because it's an unsigned integer
+    check_slot(new_unit_slot);
+    if !confirm_slot_loss(args.yes, &bank, new_unit_slot, "overwrite") {
+        println!("Action cancelled, exiting");
+        return;
+    }
+
+    bank.units[new_unit_slot] = Some(new_unit);
+
+    save_bank(args, &original, bank);
+}
+
+fn edit_account(args: &Args, Edit { original, mut bank }: Edit, assignments: Vec<(String, usize)>) {
+    let mut new_account = bank.account.clone();
+    for (field, value) in assignments {
+        new_account
+            .set_field(&field, value)
+            .or_fail("Invalid account field (use the `show` command to list them)");
+    }
+
+    println!("The following account fields will be changed:");
+    print_field_changes(bank.account.fields(), new_account.fields());
+    if confirm(
+        args.yes,
+        "Are you sure you want to save these changes? y/n ",
+    ) {
+        bank.account = new_account;
+        save_bank(args, &original, bank)
+    } else {
+        println!("Action cancelled, exiting")
+    }
+}
+
+/// Sets the given stats of the runling in `slot`, where `stats` holds the new value of each stat
+/// that is changed
+fn edit_unit(
+    args: &Args,
+    Edit { original, mut bank }: Edit,
+    slot: usize,
+    stats: [(&str, Option<usize>); 8],
+) {
+    check_slot(slot);
+    let unit = bank.units[slot].as_ref().unwrap_or_else(|| {
+        fail(&format!(
+            "Slot {slot} is empty, so there is no runling to edit"
+        ))
+    });
+
+    let mut new_unit = unit.clone();
+    for (field, value) in stats {
+        if let Some(value) = value {
+            new_unit
+                .set_field(field, value)
+                .or_fail("Invalid runling stat");
+        }
+    }
+
+    if new_unit == *unit {
+        println!("No stats were changed, exiting");
+        return;
+    }
+    println!("The following stats of the runling in slot {slot} will be changed:");
+    print_field_changes(unit.fields(), new_unit.fields());
+    if confirm(
+        args.yes,
+        "Are you sure you want to save these changes? y/n ",
+    ) {
+        bank.units[slot] = Some(new_unit);
+        save_bank(args, &original, bank)
+    } else {
+        println!("Action cancelled, exiting")
+    }
+}
+
+fn delete_unit(args: &Args, Edit { original, mut bank }: Edit, slot: usize) {
+    check_slot(slot);
+    if bank.units[slot].is_none() {
+        fail(&format!("Slot {slot} is already empty, nothing to delete"));
+    }
+    if confirm_slot_loss(args.yes, &bank, slot, "delete") {
+        bank.units[slot] = None;
+        save_bank(args, &original, bank)
+    } else {
+        println!("Action cancelled, exiting")
+    }
+}
+
+fn move_unit(args: &Args, Edit { original, mut bank }: Edit, from: usize, to: usize) {
+    check_slot(from);
+    check_slot(to);
+    if from == to {
+        fail("Cannot move a runling onto its own slot");
+    }
+    if bank.units[from].is_none() {
+        fail(&format!("Slot {from} is empty, nothing to move"));
+    }
+    if confirm_slot_loss(args.yes, &bank, to, "overwrite") {
+        bank.units[to] = bank.units[from].take();
+        save_bank(args, &original, bank)
+    } else {
+        println!("Action cancelled, exiting")
+    }
+}
+
+fn swap_units(args: &Args, Edit { original, mut bank }: Edit, first: usize, second: usize) {
+    check_slot(first);
+    check_slot(second);
+    if first == second {
+        fail("Cannot swap a slot with itself");
+    }
+    let describe = |slot: usize| {
+        bank.units[slot]
+            .as_ref()
+            .map_or_else(|| "nothing".to_string(), describe_unit)
+    };
+    if confirm(
+        args.yes,
+        &format!(
+            "Are you sure you want to swap {} in slot {first} with {} in slot {second}? y/n ",
+            describe(first),
+            describe(second)
+        ),
+    ) {
+        bank.units.swap(first, second);
+        save_bank(args, &original, bank)
+    } else {
+        println!("Action cancelled, exiting")
+    }
+}
+
+fn clone_unit(args: &Args, Edit { original, mut bank }: Edit, from: usize, to: usize) {
+    check_slot(from);
+    check_slot(to);
+    if from == to {
+        fail("Cannot clone a runling onto its own slot");
+    }
+    if bank.units[from].is_none() {
+        fail(&format!("Slot {from} is empty, nothing to clone"));
+    }
+    if confirm_slot_loss(args.yes, &bank, to, "overwrite") {
+        bank.units[to] = bank.units[from].clone();
+        save_bank(args, &original, bank)
+    } else {
+        println!("Action cancelled, exiting")
+    }
+}
+
+fn restore(
+    args: &Args,
+    OpenBank {
+        xml_bank,
+        bank_path,
+        region,
+    }: OpenBank,
+    from: &str,
+) {
+    if !Path::new(from).exists() {
+        fail("Passed backup file does not exist");
+    }
+    if let Some(backup_location) = &args.backup_location {
+        if same_file(backup_location, from) {
+            fail("The backup location must not be the file being restored, since the current bank is saved there first");
+        }
+    }
+
+    let backup = load_xml_bank(from);
+    let player_handle = bank_path.as_ref().map(|bank_path| bank_path.player.id);
+    let player_error = xml_bank
+        .check_restore(&backup, &region, player_handle)
+        .or_fail("The backup cannot be restored");
+    if let Some(player_error) = player_error {
+        eprintln!(
+            "Warning: the bank's player handle could not be derived ({player_error}), so it is not checked that the backup belongs to the same player"
+        );
+    }
+
+    if args.dry_run {
+        // The backup passed verification, so its player handle can be derived
+        let backup_player_handle = decode_bank(backup.clone(), region).player_handle;
+        print_diff(&xml_bank, &backup, region, backup_player_handle);
+        println!("Dry run, so the bank file was not changed");
+    } else if confirm(
+        args.yes,
+        "Are you sure you want to replace your bank with the backup? y/n ",
+    ) {
+        create_backup(args);
+        fs::copy(from, args.bank_file()).or_fail("Failed to copy the backup over the bank file");
+        println!("Successfully restored the backup");
+    } else {
+        println!("Action cancelled, exiting")
+    }
+}
+
+fn main() {
+    let mut args = Args::parse();
+    // Commands that work on a bank file open it first, which also finds it if no
+    // --file-location was given
+    match args.command.clone() {
+        Command::Discover => print_found_banks(&discover_banks(&args)),
+        Command::RecoverKey { samples, alphabet } => print_recovered_key(&samples, &alphabet),
+        Command::FindLayout {
+            samples,
+            encrypted,
+            max_vals,
+            max_radix,
+            limit,
+        } => print_layouts(&samples, encrypted, max_vals.as_deref(), max_radix, limit),
+        Command::Starcode { command } => run_starcode_command(&command),
+        Command::Show => show(open_bank(&mut args)),
+        Command::Verify => verify(open_bank(&mut args)),
+        Command::Resign {
+            bank_name,
+            player_handle,
+        } => {
+            let opened = open_bank(&mut args);
+            resign(&args, opened, bank_name.as_deref(), player_handle);
+        }
         Command::UnlockAllUnits => {
-            if get_user_input(&format!(
-                "Are you sure you want to overwrite your current score of {} to 50,000? y/n ",
-                bank.account.total_score
-            )) == "y"
-            {
-                bank.account.total_score = 50000;
-                save_bank(bank, &args.file_location)
-            } else {
-                println!("Action cancelled, exiting")
-            }
+            let edit = open_for_edit(&mut args);
+            unlock_all_units(&args, edit);
         }
         Command::CreateNewUnit {
             runling_type,
             level,
+            slot,
+        } => {
+            let edit = open_for_edit(&mut args);
+            create_new_unit(&args, edit, runling_type, level, slot);
+        }
+        Command::EditAccount { assignments } => {
+            let edit = open_for_edit(&mut args);
+            edit_account(&args, edit, assignments);
+        }
+        Command::EditUnit {
+            slot,
+            experience,
+            energy_regeneration,
+            maximum_energy,
+            speed,
+            skill_1_level,
+            skill_2_level,
+            runling_level,
+            remaining_points,
         } => {
-            let new_unit = Runling {
-                class: runling_type.to_bank_data(),
-                experience: Runling::experience_from_level(level),
-                energy_regeneration: 0,
-                maximum_energy: 0,
-                speed: 0,
-                skill_1_level: 0,
-                skill_2_level: 0,
-                runling_level: level,
-                remaining_points: level * 4,
-            };
-
-            let empty_save_slots = (0..=7_usize)
-                .filter(|i| bank.units[*i] == None)
-                .collect::<Vec<_>>();
-
-            let new_unit_slot = get_user_input(&format!("Which save slot would you like to overwrite with the new unit? Currently slots {empty_save_slots:?} are empty. If you choose to overwrite a full save slot, a confirmation prompt with the unit information will be shown first. \n> "));
-            let new_unit_slot = new_unit_slot
-                .parse::<usize>()
-                .expect("Must input a valid positive number");
-            // Don't need to check whether it's greater than zero because it's an unsigned integer
-            assert!(
-                new_unit_slot <= 7,
-                "Must input a number between 0 and 7, inclusive."
-            );
-            if !empty_save_slots.contains(&new_unit_slot) {
-                println!("The chosen slot currently has a runling with the following info in it:");
-                println!("{:#?}", bank.units[new_unit_slot]);
-                if get_user_input("Are you sure you want to overwrite it? y/n ") != "y" {
-                    panic!("Aborting");
-                }
+            let edit = open_for_edit(&mut args);
+            let stats = [
+                ("experience", experience),
+                ("energy_regeneration", energy_regeneration),
+                ("maximum_energy", maximum_energy),
+                ("speed", speed),
+                ("skill_1_level", skill_1_level),
+                ("skill_2_level", skill_2_level),
+                ("runling_level", runling_level),
+                ("remaining_points", remaining_points),
+            ];
+            edit_unit(&args, edit, slot, stats);
+        }
+        Command::DeleteUnit { slot } => {
+            let edit = open_for_edit(&mut args);
+            delete_unit(&args, edit, slot);
+        }
+        Command::MoveUnit { from, to } => {
+            let edit = open_for_edit(&mut args);
+            move_unit(&args, edit, from, to);
+        }
+        Command::SwapUnits { first, second } => {
+            let edit = open_for_edit(&mut args);
+            swap_units(&args, edit, first, second);
+        }
+        Command::CloneUnit { from, to } => {
+            let edit = open_for_edit(&mut args);
+            clone_unit(&args, edit, from, to);
+        }
+        Command::Restore { from } => {
+            let opened = open_bank(&mut args);
+            if !args.dry_run {
+                check_backup_location(&args);
             }
-
-            bank.units[new_unit_slot] = Some(new_unit);
-
-            save_bank(bank, &args.file_location);
+            restore(&args, opened, &from);
         }
     }
 }
@@ -0,0 +1,107 @@
+//! Parsing of the values the command line tool takes as arguments. Only part of the library with
+//! the `cli` feature.
+
+use crate::error::{Error, Result};
+
+/// Parses a comma separated list of numbers
+pub fn parse_numbers(s: &str) -> Result<Vec<usize>> {
+    s.split(',')
+        .map(|n| {
+            n.trim()
+                .parse()
+                .map_err(|_| Error::InvalidNumber(n.to_string()))
+        })
+        .collect()
+}
+
+/// Parses a comma separated list of numbers in which unknown ones are written as `?`
+pub fn parse_optional_numbers(s: &str) -> Result<Vec<Option<usize>>> {
+    s.split(',')
+        .map(|n| match n.trim() {
+            "?" => Ok(None),
+            n => n
+                .parse()
+                .map(Some)
+                .map_err(|_| Error::InvalidNumber(n.to_string())),
+        })
+        .collect()
+}
+
+/// Parses a `FIELD=VALUE` assignment
+pub fn parse_assignment(s: &str) -> Result<(String, usize)> {
+    let (field, value) = s.split_once('=').ok_or_else(|| Error::UnexpectedFormat {
+        expected: "FIELD=VALUE",
+        found: s.to_string(),
+    })?;
+    let value = value
+        .trim()
+        .parse()
+        .map_err(|_| Error::InvalidNumber(value.to_string()))?;
+    Ok((field.trim().to_string(), value))
+}
+
+/// Removes the line break that ends input read from stdin. Nothing else is trimmed, since spaces
+/// are part of the starcode alphabet.
+pub fn strip_line_break(input: &str) -> &str {
+    let input = input.strip_suffix('\n').unwrap_or(input);
+    input.strip_suffix('\r').unwrap_or(input)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        error::Error,
+        parse::{parse_assignment, parse_numbers, parse_optional_numbers, strip_line_break},
+    };
+
+    #[test]
+    fn numbers() {
+        assert_eq!(
+            parse_numbers("300000, 8100000,10").unwrap(),
+            [300000, 8100000, 10]
+        );
+        assert_eq!(
+            parse_numbers(&usize::MAX.to_string()).unwrap(),
+            [usize::MAX]
+        );
+        assert!(matches!(parse_numbers("1,-2"), Err(Error::InvalidNumber(n)) if n == "-2"));
+        assert!(parse_numbers("1,,2").is_err());
+    }
+
+    #[test]
+    fn optional_numbers() {
+        assert_eq!(
+            parse_optional_numbers("1, ?,15").unwrap(),
+            [Some(1), None, Some(15)]
+        );
+        assert!(matches!(
+            parse_optional_numbers("1,??"),
+            Err(Error::InvalidNumber(n)) if n == "??"
+        ));
+    }
+
+    #[test]
+    fn assignments() {
+        assert_eq!(
+            parse_assignment(" total_score = 50000").unwrap(),
+            ("total_score".to_string(), 50000)
+        );
+        assert!(matches!(
+            parse_assignment("total_score"),
+            Err(Error::UnexpectedFormat { .. })
+        ));
+        assert!(matches!(
+            parse_assignment("total_score=-1"),
+            Err(Error::InvalidNumber(_))
+        ));
+    }
+
+    #[test]
+    fn line_breaks() {
+        assert_eq!(strip_line_break("abc\n"), "abc");
+        assert_eq!(strip_line_break("abc\r\n"), "abc");
+        // Only the final line break goes, and spaces are part of the value
+        assert_eq!(strip_line_break(" a c \n\n"), " a c \n");
+        assert_eq!(strip_line_break("abc"), "abc");
+    }
+}
@@ -0,0 +1,173 @@
+/// How one field of a [`starcode_record!`](crate::starcode_record) is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldSpec {
+    pub name: &'static str,
+    /// The largest value the field can be stored with
+    pub max_val: usize,
+    /// Whether the field adds to the record's checksum
+    pub checksum: bool,
+}
+
+/// Defines a record that is stored in a bank as a single starcode value. Each field is listed once,
+/// in the order it is stored, with the largest value it can be stored with. Fields marked
+/// `+ checksum` add up to the record's checksum.
+///
+/// ```
+/// runling_run_edit::starcode_record! {
+///     #[derive(Debug, PartialEq)]
+///     pub struct Score {
+///         games: 1000,
+///         points: 50000 + checksum,
+///     }
+/// }
+///
+/// let score = Score { games: 3, points: 120 };
+/// assert_eq!(Score::from_data(score.to_data().unwrap()).unwrap(), score);
+/// assert_eq!(score.checksum(), 120);
+///
+/// let codec = runling_run_edit::starcode::Codec::new("0123456789abcdef", "c0de").unwrap();
+/// assert_eq!(Score::decode(&codec, &score.encode(&codec).unwrap()).unwrap(), score);
+/// ```
+///
+/// The record gets `decode` and `encode` to read and write it with any
+/// [`Codec`](crate::starcode::Codec), `from_data` and `to_data` to do so with Runling Run's,
+/// `checksum`, and the field metadata `SCHEMA`, `fields`, `max_val`, `validate`, `field_mut` and
+/// `set_field`.
+#[macro_export]
+macro_rules! starcode_record {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident {
+            $(
+                $(#[$field_meta:meta])*
+                $field:ident: $max_val:literal $(+ $checksum:ident)?
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        pub struct $name {
+            $(
+                $(#[$field_meta])*
+                pub $field: usize,
+            )*
+        }
+        impl $name {
+            /// Every field, in the order they are stored in the bank
+            pub const SCHEMA: [$crate::record::FieldSpec; [$(stringify!($field)),*].len()] = [
+                $(
+                    $crate::record::FieldSpec {
+                        name: stringify!($field),
+                        max_val: $max_val,
+                        checksum: $crate::starcode_record!(@checksum $($checksum)?),
+                    },
+                )*
+            ];
+
+            /// Decodes the record with the [Runling Run codec](crate::starcode::Codec::runling_run)
+            pub fn from_data(data: String) -> $crate::error::Result<Self> {
+                Self::decode($crate::starcode::Codec::runling_run(), &data)
+            }
+
+            /// Encodes the record with the [Runling Run codec](crate::starcode::Codec::runling_run)
+            pub fn to_data(&self) -> $crate::error::Result<String> {
+                self.encode($crate::starcode::Codec::runling_run())
+            }
+
+            pub fn decode(
+                codec: &$crate::starcode::Codec,
+                data: &str,
+            ) -> $crate::error::Result<Self> {
+                let data = codec.decrypt(data)?;
+                let mut reader = $crate::starcode::StarcodeReader::new(codec.uncompress(&data)?);
+
+                // Fields come out in the reverse of the order they were stored in
+                let mut values = [0; Self::SCHEMA.len()];
+                for (value, spec) in values.iter_mut().zip(Self::SCHEMA).rev() {
+                    *value = reader.get_int(spec.max_val);
+                }
+                reader.finish()?;
+                let [$($field),*] = values;
+                Ok(Self { $($field),* })
+            }
+
+            pub fn encode(
+                &self,
+                codec: &$crate::starcode::Codec,
+            ) -> $crate::error::Result<String> {
+                self.validate()?;
+                let mut writer = $crate::starcode::StarcodeWriter::new();
+                for ((_, value), spec) in self.fields().into_iter().zip(Self::SCHEMA) {
+                    writer.store_int(value, spec.max_val)?;
+                }
+                codec.encrypt(&codec.compress(writer.data()))
+            }
+
+            /// All fields by name, in the order they are stored in the bank
+            pub fn fields(&self) -> [(&'static str, usize); Self::SCHEMA.len()] {
+                [$((stringify!($field), self.$field)),*]
+            }
+
+            /// The largest value the named field can be stored with, or `None` if there is no such
+            /// field
+            pub fn max_val(field: &str) -> Option<usize> {
+                Self::SCHEMA
+                    .iter()
+                    .find(|spec| spec.name == field)
+                    .map(|spec| spec.max_val)
+            }
+
+            /// Checks every field against its maximum value
+            pub fn validate(&self) -> $crate::error::Result<()> {
+                for ((field, value), spec) in self.fields().into_iter().zip(Self::SCHEMA) {
+                    if value > spec.max_val {
+                        return Err($crate::error::Error::FieldOutOfRange {
+                            field,
+                            value,
+                            max_val: spec.max_val,
+                        });
+                    }
+                }
+                Ok(())
+            }
+
+            /// Sets the named field, checking the value against the field's maximum
+            pub fn set_field(&mut self, field: &str, value: usize) -> $crate::error::Result<()> {
+                let spec = Self::SCHEMA
+                    .iter()
+                    .find(|spec| spec.name == field)
+                    .ok_or_else(|| $crate::error::Error::UnknownField(field.to_string()))?;
+                if value > spec.max_val {
+                    return Err($crate::error::Error::FieldOutOfRange {
+                        field: spec.name,
+                        value,
+                        max_val: spec.max_val,
+                    });
+                }
+                *self.field_mut(field).unwrap() = value;
+                Ok(())
+            }
+
+            pub fn field_mut(&mut self, field: &str) -> Option<&mut usize> {
+                match field {
+                    $(stringify!($field) => Some(&mut self.$field),)*
+                    _ => None,
+                }
+            }
+
+            pub fn checksum(&self) -> usize {
+                self.fields()
+                    .into_iter()
+                    .zip(Self::SCHEMA)
+                    .filter(|(_, spec)| spec.checksum)
+                    .map(|((_, value), _)| value)
+                    .sum()
+            }
+        }
+    };
+    (@checksum) => {
+        false
+    };
+    (@checksum checksum) => {
+        true
+    };
+}
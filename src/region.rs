@@ -0,0 +1,220 @@
+use std::{fmt, str::FromStr};
+
+use crate::{
+    bank_path::BankPath,
+    error::{Error, Result},
+};
+
+/// The Battle.net regions, which make up the first part of a handle like `1-S2-1-417073`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Region {
+    Americas,
+    Europe,
+    /// Korea and Taiwan
+    Asia,
+    China,
+}
+impl Region {
+    pub const ALL: [Region; 4] = [
+        Region::Americas,
+        Region::Europe,
+        Region::Asia,
+        Region::China,
+    ];
+
+    pub fn id(self) -> u8 {
+        match self {
+            Region::Americas => 1,
+            Region::Europe => 2,
+            Region::Asia => 3,
+            Region::China => 5,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|region| region.id() == id)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Region::Americas => "Americas",
+            Region::Europe => "Europe",
+            Region::Asia => "Korea/Taiwan",
+            Region::China => "China",
+        }
+    }
+
+    /// The handle that Runling Run 4 is published under in this region, if it is known. For other
+    /// regions it can be read from the name of the folder the bank is in, see
+    /// [`BankPath`](crate::bank_path::BankPath).
+    pub fn runling_run_author(self) -> Option<Handle> {
+        match self {
+            Region::Americas => Some(Handle {
+                region: Region::Americas,
+                realm: 1,
+                id: 417073,
+            }),
+            Region::Europe | Region::Asia | Region::China => None,
+        }
+    }
+}
+
+/// A Battle.net handle, written as `<region>-S2-<realm>-<id>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Handle {
+    pub region: Region,
+    pub realm: u8,
+    pub id: usize,
+}
+impl fmt::Display for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-S2-{}-{}", self.region.id(), self.realm, self.id)
+    }
+}
+impl FromStr for Handle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidHandle(s.to_string());
+        let mut parts = s.split('-');
+        let (Some(region), Some("S2"), Some(realm), Some(id), None) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) else {
+            return Err(invalid());
+        };
+        Ok(Handle {
+            region: region
+                .parse()
+                .ok()
+                .and_then(Region::from_id)
+                .ok_or_else(invalid)?,
+            realm: realm.parse().map_err(|_| invalid())?,
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// The region a bank belongs to, which decides the handles its signature is computed with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BankRegion {
+    pub author: Handle,
+    pub player_realm: u8,
+}
+impl BankRegion {
+    /// Runling Run 4's bank in the given region. Fails if the author's handle in that region is
+    /// not known, in which case it has to be given with [`BankRegion::with_author`] instead.
+    pub fn runling_run(region: Region) -> Result<Self> {
+        let author = region
+            .runling_run_author()
+            .ok_or(Error::UnknownAuthorHandle(region.name()))?;
+        Ok(Self::with_author(author))
+    }
+
+    /// A bank of the map published under `author`, for a player on the same realm
+    pub fn with_author(author: Handle) -> Self {
+        Self {
+            author,
+            player_realm: author.realm,
+        }
+    }
+
+    /// Works out the region a bank is signed with: `author`'s if it is given, and otherwise
+    /// Runling Run's in `region`, or the region of the folder the bank is in. Without any of them
+    /// it is the Americas. When the folder is in `region` as well, its handles are used, since
+    /// they work even where Runling Run's author is not known.
+    pub fn resolve(
+        author: Option<Handle>,
+        region: Option<Region>,
+        bank_path: Option<&BankPath>,
+    ) -> Result<Self> {
+        match (author, region, bank_path) {
+            (Some(author), _, _) => Ok(Self::with_author(author)),
+            (None, Some(region), Some(bank_path)) if bank_path.author.region == region => {
+                Ok(bank_path.region())
+            }
+            (None, Some(region), _) => Self::runling_run(region),
+            (None, None, Some(bank_path)) => Ok(bank_path.region()),
+            (None, None, None) => Ok(Self::default()),
+        }
+    }
+
+    pub fn region(&self) -> Region {
+        self.author.region
+    }
+
+    pub fn player_handle(&self, id: usize) -> Handle {
+        Handle {
+            region: self.author.region,
+            realm: self.player_realm,
+            id,
+        }
+    }
+}
+impl Default for BankRegion {
+    fn default() -> Self {
+        Self::runling_run(Region::Americas).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::{
+        bank_path::BankPath,
+        error::Error,
+        region::{BankRegion, Handle, Region},
+    };
+
+    #[test]
+    fn handle_round_trip() {
+        let handle: Handle = "2-S2-1-7654321".parse().unwrap();
+        assert_eq!(
+            handle,
+            Handle {
+                region: Region::Europe,
+                realm: 1,
+                id: 7654321
+            }
+        );
+        assert_eq!(handle.to_string(), "2-S2-1-7654321");
+
+        assert!("4-S2-1-1".parse::<Handle>().is_err());
+        assert!("1-S1-1-1".parse::<Handle>().is_err());
+        assert!("1-S2-1".parse::<Handle>().is_err());
+        assert!("1-S2-1-1-1".parse::<Handle>().is_err());
+    }
+
+    #[test]
+    fn resolves_bank_region() {
+        let europe_path = BankPath::parse(Path::new(
+            "12345678/2-S2-2-111/Banks/2-S2-1-222/RunlingRun004.SC2Bank",
+        ));
+        let author = "1-S2-2-333".parse::<Handle>().unwrap();
+
+        let region = BankRegion::resolve(Some(author), Some(Region::Europe), europe_path.as_ref());
+        assert_eq!(region.unwrap(), BankRegion::with_author(author));
+        let region = BankRegion::resolve(None, None, europe_path.as_ref()).unwrap();
+        assert_eq!(region.author.to_string(), "2-S2-1-222");
+        assert_eq!(region.player_realm, 2);
+        // The folder's handles are used for its own region even though the author there is not
+        // known, but not for another one
+        let region = BankRegion::resolve(None, Some(Region::Europe), europe_path.as_ref());
+        assert_eq!(region.unwrap().author.to_string(), "2-S2-1-222");
+        let region = BankRegion::resolve(None, Some(Region::Americas), europe_path.as_ref());
+        assert_eq!(region.unwrap(), BankRegion::default());
+        assert!(matches!(
+            BankRegion::resolve(None, Some(Region::Asia), None),
+            Err(Error::UnknownAuthorHandle("Korea/Taiwan"))
+        ));
+        assert_eq!(
+            BankRegion::resolve(None, None, None).unwrap(),
+            BankRegion::default()
+        );
+    }
+}
@@ -1,99 +1,317 @@
-use num::{BigInt, FromPrimitive};
+use std::sync::LazyLock;
 
-const ALPHABET: &str =
+use num::{BigUint, Integer, ToPrimitive, Zero};
+
+use crate::error::{Error, Result};
+
+pub const RUNLING_RUN_ALPHABET: &str =
     "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ!$%/()=?,.-;:_^#+* @{[]}|~`";
-const ENCRYPTION_KEY: &str = "WalkerKey";
-
-fn alphabet_index(c: char) -> usize {
-    ALPHABET
-        .char_indices()
-        .find_map(|(i, a)| if a == c { Some(i) } else { None })
-        .unwrap()
-}
-
-fn shift_backward(c: char, d: char) -> char {
-    let c_index = alphabet_index(c);
-    let d_index = alphabet_index(d);
-    let result_index = ((c_index + ALPHABET.len()) - d_index) % ALPHABET.len();
-    ALPHABET.chars().nth(result_index).unwrap()
-}
-fn shift_forward(c: char, d: char) -> char {
-    let c_index = alphabet_index(c);
-    let d_index = alphabet_index(d);
-    let result_index = ((c_index + ALPHABET.len()) + d_index) % ALPHABET.len();
-    ALPHABET.chars().nth(result_index).unwrap()
-}
-
-pub fn decrypt(s: String) -> String {
-    let mut result: String = String::new();
-    for (i, c) in s.char_indices() {
-        let key_index = i % ENCRYPTION_KEY.len();
-        result.push(shift_backward(
-            c,
-            ENCRYPTION_KEY.chars().nth(key_index).unwrap(),
-        ))
-    }
-    result
-}
-pub fn encrypt(s: String) -> String {
-    let mut result: String = String::new();
-    for (i, c) in s.char_indices() {
-        let key_index = i % ENCRYPTION_KEY.len();
-        result.push(shift_forward(
-            c,
-            ENCRYPTION_KEY.chars().nth(key_index).unwrap(),
-        ))
-    }
-    result
-}
-
-pub fn compress(mut s: BigInt) -> String {
-    let base = BigInt::from_usize(ALPHABET.len()).unwrap();
-    let mut remainder: BigInt;
-    let mut result = String::new();
-
-    while s != BigInt::ZERO {
-        remainder = &s % &base;
-        result.push(
-            ALPHABET
-                .chars()
-                .nth(remainder.to_string().parse().unwrap())
-                .unwrap(),
-        );
-        s = s / &base;
+pub const RUNLING_RUN_KEY: &str = "WalkerKey";
+
+static RUNLING_RUN: LazyLock<Codec> =
+    LazyLock::new(|| Codec::new(RUNLING_RUN_ALPHABET, RUNLING_RUN_KEY).unwrap());
+
+const INVALID: u8 = u8::MAX;
+
+/// The StarCode scheme that Galaxy maps store values in: numbers are written in base
+/// `alphabet.len()` using the alphabet's characters as digits, and then encrypted by shifting each
+/// character along the alphabet by the matching character of the repeating key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Codec {
+    alphabet: Vec<u8>,
+    /// The position of each ASCII character in the alphabet, or [`INVALID`] if it is not in it
+    index: [u8; 128],
+    /// The key as positions in the alphabet
+    key_shifts: Vec<u8>,
+}
+impl Codec {
+    /// A codec for the given alphabet and key. The alphabet has to be made up of at least two
+    /// different ASCII characters, and the key of characters from the alphabet.
+    pub fn new(alphabet: &str, key: &str) -> Result<Self> {
+        if alphabet.len() < 2 || !alphabet.is_ascii() {
+            return Err(Error::InvalidCodec(
+                "the alphabet needs at least two characters, all of them ASCII",
+            ));
+        }
+        let mut index = [INVALID; 128];
+        for (i, c) in alphabet.bytes().enumerate() {
+            if index[c as usize] != INVALID {
+                return Err(Error::InvalidCodec(
+                    "the alphabet has a character more than once",
+                ));
+            }
+            index[c as usize] = i as u8;
+        }
+
+        let mut codec = Self {
+            alphabet: alphabet.as_bytes().to_vec(),
+            index,
+            key_shifts: Vec::new(),
+        };
+        codec.key_shifts = key
+            .chars()
+            .map(|c| codec.alphabet_index(c))
+            .collect::<Result<_>>()
+            .map_err(|_| {
+                Error::InvalidCodec("the key has a character that is not in the alphabet")
+            })?;
+        if codec.key_shifts.is_empty() {
+            return Err(Error::InvalidCodec("the key is empty"));
+        }
+        Ok(codec)
+    }
+
+    /// The codec Runling Run 4 stores its bank in
+    pub fn runling_run() -> &'static Self {
+        &RUNLING_RUN
+    }
+
+    pub fn alphabet(&self) -> &str {
+        std::str::from_utf8(&self.alphabet).unwrap()
+    }
+
+    pub fn key(&self) -> String {
+        self.key_shifts
+            .iter()
+            .map(|&shift| self.alphabet[shift as usize] as char)
+            .collect()
+    }
+
+    pub(crate) fn alphabet_index(&self, c: char) -> Result<u8> {
+        match self.index.get(c as usize) {
+            Some(&index) if index != INVALID => Ok(index),
+            _ => Err(Error::InvalidCharacter(c)),
+        }
+    }
+
+    /// Shifts every character by the key, as positions in the alphabet
+    fn shift(&self, s: &str, shift_index: impl Fn(u8, u8) -> u8) -> Result<String> {
+        s.chars()
+            .zip(self.key_shifts.iter().cycle())
+            .map(|(c, &key_shift)| {
+                let index = shift_index(self.alphabet_index(c)?, key_shift);
+                Ok(self.alphabet[index as usize % self.alphabet.len()] as char)
+            })
+            .collect()
+    }
+
+    pub fn decrypt(&self, s: &str) -> Result<String> {
+        let len = self.alphabet.len() as u8;
+        self.shift(s, |index, key_shift| index + len - key_shift)
+    }
+
+    pub fn encrypt(&self, s: &str) -> Result<String> {
+        self.shift(s, |index, key_shift| index + key_shift)
+    }
+
+    pub fn compress(&self, s: &BigUint) -> String {
+        if s.is_zero() {
+            return String::new();
+        }
+        let digits = s.to_radix_be(self.alphabet.len() as u32);
+        digits
+            .into_iter()
+            .map(|digit| self.alphabet[digit as usize] as char)
+            .collect()
     }
 
-    result.chars().rev().collect()
+    pub fn uncompress(&self, s: &str) -> Result<BigUint> {
+        let digits = s
+            .chars()
+            .map(|c| self.alphabet_index(c))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(BigUint::from_radix_be(&digits, self.alphabet.len() as u32).unwrap())
+    }
+}
+
+/// [`Codec::decrypt`] with the [Runling Run codec](Codec::runling_run)
+pub fn decrypt(s: String) -> Result<String> {
+    RUNLING_RUN.decrypt(&s)
+}
+/// [`Codec::encrypt`] with the [Runling Run codec](Codec::runling_run)
+pub fn encrypt(s: String) -> Result<String> {
+    RUNLING_RUN.encrypt(&s)
+}
+/// [`Codec::compress`] with the [Runling Run codec](Codec::runling_run)
+pub fn compress(s: BigUint) -> String {
+    RUNLING_RUN.compress(&s)
+}
+/// [`Codec::uncompress`] with the [Runling Run codec](Codec::runling_run)
+pub fn uncompress(s: String) -> Result<BigUint> {
+    RUNLING_RUN.uncompress(&s)
 }
-pub fn uncompress(s: String) -> BigInt {
-    let base = BigInt::from_usize(ALPHABET.len()).unwrap();
-    let mut power = BigInt::from_usize(1).unwrap();
-    let mut result = BigInt::ZERO;
 
-    for c in s.chars().rev() {
-        let index = alphabet_index(c);
-        let digit_value = &power * BigInt::from_usize(index).unwrap();
-        result = result + digit_value;
-        power = &power * &base;
+/// Parses a payload, the number a starcode value uncompresses to. Payloads are never negative.
+pub fn parse_payload(s: &str) -> Result<BigUint> {
+    s.trim()
+        .parse()
+        .map_err(|_| Error::InvalidNumber(s.to_string()))
+}
+
+/// The number a field with the given maximum is stored in base of, which may not fit a `usize`
+fn radix(max_val: usize) -> BigUint {
+    BigUint::from(max_val) + 1_u8
+}
+
+pub fn get_int(s: &mut BigUint, max_val: usize) -> usize {
+    let (quotient, val) = s.div_rem(&radix(max_val));
+    *s = quotient;
+    val.to_usize().unwrap()
+}
+pub fn store_int(s: &mut BigUint, val: usize, max_val: usize) -> Result<()> {
+    if val > max_val {
+        return Err(Error::ValueTooLarge {
+            value: val,
+            max_val,
+        });
     }
 
-    result
+    *s *= radix(max_val);
+    *s += val;
+    Ok(())
 }
 
-pub fn get_int(s: &mut BigInt, max_val: usize) -> usize {
-    let max_val = BigInt::from_usize(max_val + 1).unwrap();
-    let val = &*s % &max_val;
-    *s = &*s / max_val;
+/// Splits `data` into fields with the given maximum values, in the order they are stored. Fails if
+/// there is data left over after the first field.
+pub fn unpack(data: BigUint, max_vals: &[usize]) -> Result<Vec<usize>> {
+    let mut reader = StarcodeReader::new(data);
+    let mut values = max_vals
+        .iter()
+        .rev()
+        .map(|&max_val| reader.get_int(max_val))
+        .collect::<Vec<_>>();
+    reader.finish()?;
+    values.reverse();
+    Ok(values)
+}
 
-    val.to_string().parse().unwrap()
+/// Stores fields with the given maximum values in a payload, the reverse of [`unpack`]
+pub fn pack(values: &[usize], max_vals: &[usize]) -> Result<BigUint> {
+    if values.len() != max_vals.len() {
+        return Err(Error::FieldCountMismatch {
+            values: values.len(),
+            fields: max_vals.len(),
+        });
+    }
+    let mut writer = StarcodeWriter::new();
+    for (&value, &max_val) in values.iter().zip(max_vals) {
+        writer.store_int(value, max_val)?;
+    }
+    Ok(writer.into_data())
 }
-pub fn store_int(s: &mut BigInt, val: usize, max_val: usize) {
-    assert!(val <= max_val);
 
-    let max_val = BigInt::from_usize(max_val + 1).unwrap();
-    let val = BigInt::from_usize(val).unwrap();
+/// Where a field sits in a payload. Fields are numbered from the last one stored, because that is
+/// the first one read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldPosition {
+    pub index: usize,
+    pub max_val: usize,
+    /// What the field's value is multiplied by in the payload
+    pub place_value: BigUint,
+}
 
-    *s = (&*s * max_val) + val;
+/// Reads fields off an uncompressed payload like [`get_int`], keeping track of the fields read so
+/// that leftover data can be reported
+#[derive(Debug, Clone)]
+pub struct StarcodeReader {
+    data: BigUint,
+    place_value: BigUint,
+    positions: Vec<FieldPosition>,
+}
+impl StarcodeReader {
+    pub fn new(data: BigUint) -> Self {
+        Self {
+            data,
+            place_value: BigUint::from(1_u8),
+            positions: Vec::new(),
+        }
+    }
+
+    /// Reads the last field that is still stored
+    pub fn get_int(&mut self, max_val: usize) -> usize {
+        self.positions.push(FieldPosition {
+            index: self.positions.len(),
+            max_val,
+            place_value: self.place_value.clone(),
+        });
+        self.place_value *= radix(max_val);
+        get_int(&mut self.data, max_val)
+    }
+
+    pub fn fields_read(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Every field read so far, in the order they were read
+    pub fn positions(&self) -> &[FieldPosition] {
+        &self.positions
+    }
+
+    /// What has not been read yet
+    pub fn remainder(&self) -> &BigUint {
+        &self.data
+    }
+
+    /// Checks that every field has been read
+    pub fn finish(self) -> Result<()> {
+        if self.data.is_zero() {
+            Ok(())
+        } else {
+            Err(Error::LeftoverData {
+                fields: self.positions.len(),
+                remainder: self.data,
+            })
+        }
+    }
+}
+
+/// Stores fields in a payload like [`store_int`], keeping track of the fields stored
+#[derive(Debug, Clone, Default)]
+pub struct StarcodeWriter {
+    data: BigUint,
+    max_vals: Vec<usize>,
+}
+impl StarcodeWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn store_int(&mut self, val: usize, max_val: usize) -> Result<()> {
+        store_int(&mut self.data, val, max_val)?;
+        self.max_vals.push(max_val);
+        Ok(())
+    }
+
+    pub fn fields_written(&self) -> usize {
+        self.max_vals.len()
+    }
+
+    /// Every field stored so far, in the order a [`StarcodeReader`] reads them back
+    pub fn positions(&self) -> Vec<FieldPosition> {
+        let mut place_value = BigUint::from(1_u8);
+        self.max_vals
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(index, &max_val)| {
+                let position = FieldPosition {
+                    index,
+                    max_val,
+                    place_value: place_value.clone(),
+                };
+                place_value *= radix(max_val);
+                position
+            })
+            .collect()
+    }
+
+    pub fn data(&self) -> &BigUint {
+        &self.data
+    }
+
+    pub fn into_data(self) -> BigUint {
+        self.data
+    }
 }
 
 #[cfg(test)]
@@ -104,12 +322,148 @@ mod test {
     fn test() {
         let encrypted_runling = "/Uni.I^uVUgUsr::M3I~IeI0".to_string();
 
-        let decrypted_runling = decrypt(encrypted_runling.clone());
+        let decrypted_runling = decrypt(encrypted_runling.clone()).unwrap();
 
-        assert_eq!(encrypt(decrypted_runling.clone()), encrypted_runling);
+        assert_eq!(
+            encrypt(decrypted_runling.clone()).unwrap(),
+            encrypted_runling
+        );
 
-        let decompressed_runling = uncompress(decrypted_runling.clone());
+        let decompressed_runling = uncompress(decrypted_runling.clone()).unwrap();
 
         assert_eq!(compress(decompressed_runling.clone()), decrypted_runling);
     }
+
+    #[test]
+    fn invalid_input() {
+        assert!(matches!(
+            decrypt("ab\"c".to_string()),
+            Err(Error::InvalidCharacter('"'))
+        ));
+        assert!(matches!(
+            uncompress("aé".to_string()),
+            Err(Error::InvalidCharacter('é'))
+        ));
+        assert!(matches!(
+            store_int(&mut BigUint::from(0_u32), 11, 10),
+            Err(Error::ValueTooLarge {
+                value: 11,
+                max_val: 10
+            })
+        ));
+    }
+
+    #[test]
+    fn compress_edge_cases() {
+        assert_eq!(compress(BigUint::from(0_u32)), "");
+        assert_eq!(uncompress(String::new()).unwrap(), BigUint::from(0_u32));
+        // The last character of the alphabet is its largest digit
+        let base = RUNLING_RUN_ALPHABET.len();
+        assert_eq!(compress(BigUint::from(base - 1)), "`");
+        assert_eq!(compress(BigUint::from(base)), "10");
+        assert_eq!(
+            uncompress("00`".to_string()).unwrap(),
+            BigUint::from(base - 1)
+        );
+
+        let mut data = BigUint::from(0_u32);
+        store_int(&mut data, usize::MAX - 1, usize::MAX - 1).unwrap();
+        store_int(&mut data, 7, 10).unwrap();
+        assert_eq!(get_int(&mut data, 10), 7);
+        assert_eq!(get_int(&mut data, usize::MAX - 1), usize::MAX - 1);
+    }
+
+    #[test]
+    fn other_codecs() {
+        let hex = Codec::new("0123456789ABCDEF", "A3").unwrap();
+        assert_eq!(hex.alphabet(), "0123456789ABCDEF");
+        assert_eq!(hex.key(), "A3");
+        assert_eq!(hex.compress(&BigUint::from(0xBEEF_u32)), "BEEF");
+        // B+A, E+3, E+A, F+3 wrapping around the alphabet
+        assert_eq!(hex.encrypt("BEEF").unwrap(), "5182");
+        assert_eq!(hex.decrypt("5182").unwrap(), "BEEF");
+        assert_eq!(hex.uncompress("BEEF").unwrap(), BigUint::from(0xBEEF_u32));
+        assert!(matches!(
+            hex.uncompress("beef"),
+            Err(Error::InvalidCharacter('b'))
+        ));
+
+        let runling_run = Codec::runling_run();
+        assert_eq!(runling_run.key(), "WalkerKey");
+        assert_eq!(
+            runling_run.encrypt("0123456789").unwrap(),
+            encrypt("0123456789".to_string()).unwrap()
+        );
+
+        assert!(Codec::new("0", "0").is_err());
+        assert!(Codec::new("0120", "0").is_err());
+        assert!(Codec::new("01é", "0").is_err());
+        assert!(Codec::new("012", "3").is_err());
+        assert!(Codec::new("012", "").is_err());
+    }
+
+    #[test]
+    fn payloads() {
+        assert_eq!(parse_payload(" 123\n").unwrap(), BigUint::from(123_u32));
+        assert_eq!(parse_payload("0").unwrap(), BigUint::ZERO);
+        assert!(matches!(parse_payload("-5"), Err(Error::InvalidNumber(_))));
+        assert!(matches!(parse_payload("12a"), Err(Error::InvalidNumber(_))));
+        assert!(parse_payload("").is_err());
+    }
+
+    #[test]
+    fn pack_and_unpack() {
+        let max_vals = [300000, 8100000, 10];
+        let data = pack(&[1, 663, 10], &max_vals).unwrap();
+        assert_eq!(unpack(data.clone(), &max_vals).unwrap(), [1, 663, 10]);
+        assert!(matches!(
+            unpack(data, &max_vals[1..]),
+            Err(Error::LeftoverData { fields: 2, .. })
+        ));
+        assert!(matches!(
+            pack(&[1, 2], &max_vals),
+            Err(Error::FieldCountMismatch {
+                values: 2,
+                fields: 3
+            })
+        ));
+        assert!(matches!(
+            pack(&[11], &[10]),
+            Err(Error::ValueTooLarge { .. })
+        ));
+
+        // Fields can be as large as a usize gets
+        let data = pack(&[usize::MAX, 5], &[usize::MAX, usize::MAX]).unwrap();
+        assert_eq!(
+            unpack(data, &[usize::MAX, usize::MAX]).unwrap(),
+            [usize::MAX, 5]
+        );
+    }
+
+    #[test]
+    fn reader_and_writer() {
+        let mut writer = StarcodeWriter::new();
+        writer.store_int(3, 10).unwrap();
+        writer.store_int(7, 99).unwrap();
+        assert!(writer.store_int(5, 4).is_err());
+        assert_eq!(writer.fields_written(), 2);
+        assert_eq!(writer.data(), &BigUint::from(3_u32 * 100 + 7));
+
+        let mut reader = StarcodeReader::new(writer.data().clone());
+        assert_eq!(reader.get_int(99), 7);
+        assert_eq!(reader.get_int(10), 3);
+        assert_eq!(reader.positions(), writer.positions());
+        assert_eq!(reader.positions()[1].place_value, BigUint::from(100_u32));
+        assert!(reader.finish().is_ok());
+
+        // A field too many is left over
+        let mut reader = StarcodeReader::new(BigUint::from(2_u32 * 11 * 100 + 3 * 100 + 7));
+        reader.get_int(99);
+        reader.get_int(10);
+        assert_eq!(reader.remainder(), &BigUint::from(2_u32));
+        assert!(matches!(
+            reader.finish(),
+            Err(Error::LeftoverData { fields: 2, .. })
+        ));
+    }
 }